@@ -0,0 +1,115 @@
+// 搜索索引基准测试：cargo bench --bench search_index
+// 对比全量模糊匹配与"索引筛选候选 + 模糊匹配"在 1 万 / 10 万 / 100 万条目上的单次查询耗时
+
+// 单元测试在基准测试中不编译 (harness = false)，其中的 use 会被报告为未使用
+#[path = "../src/search_index.rs"]
+#[allow(dead_code, unused_imports)]
+mod search_index;
+
+#[path = "../src/normalize.rs"]
+#[allow(dead_code, unused_imports)]
+mod normalize;
+
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use search_index::SearchIndex;
+use std::time::{Duration, Instant};
+
+const SIZES: &[usize] = &[10_000, 100_000, 1_000_000];
+const QUERIES: &[&str] = &["c", "co", "cod", "code", "vsc", "chrm", "weixin", "qzxj"];
+const MAX_RESULTS: usize = 100;
+
+const WORDS: &[&str] = &[
+    "code", "chrome", "visual", "studio", "office", "word", "excel", "power", "point", "setup",
+    "player", "music", "video", "editor", "notepad", "terminal", "shell", "git", "python", "node",
+    "java", "steam", "game", "launcher", "manager", "driver", "update", "tool", "helper", "report",
+    "photo", "image", "viewer", "reader", "pdf", "zip", "backup", "sync", "cloud", "mail",
+    "weixin", "qq", "wangyi", "yunyinyue", "baidu", "wangpan", "kugou", "aiqiyi", "youku", "dingding",
+];
+
+// 固定种子的线性同余生成器，保证每次生成相同的数据
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self) -> usize {
+        self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (self.0 >> 33) as usize
+    }
+}
+
+struct Doc {
+    title: String,
+    pinyin: String,
+    acronym: String,
+}
+
+// 随机音节拼成的词，模拟真实文件名中大量不重复的专有名词
+fn random_word(rng: &mut Lcg) -> String {
+    const CONSONANTS: &[u8] = b"bcdfghjklmnprstvwxyz";
+    const VOWELS: &[u8] = b"aeiou";
+    (0..2 + rng.next() % 3)
+        .flat_map(|_| [CONSONANTS[rng.next() % CONSONANTS.len()], VOWELS[rng.next() % VOWELS.len()]])
+        .map(char::from)
+        .collect()
+}
+
+// 约三分之一的词来自常见词表，其余为随机词
+fn generate(n: usize) -> Vec<Doc> {
+    let mut rng = Lcg(42);
+    (0..n)
+        .map(|i| {
+            let words: Vec<String> = (0..1 + rng.next() % 3)
+                .map(|_| if rng.next() % 6 < 2 { WORDS[rng.next() % WORDS.len()].to_string() } else { random_word(&mut rng) })
+                .collect();
+            let title = format!("{} {}", words.join(" "), i % 1000);
+            let pinyin = title.to_lowercase();
+            let acronym = words.iter().map(|w| &w[..1]).collect();
+            Doc { title, pinyin, acronym }
+        })
+        .collect()
+}
+
+fn score(matcher: &SkimMatcherV2, doc: &Doc, query: &str) -> Option<i64> {
+    matcher.fuzzy_match(&doc.title, query).or_else(|| {
+        if doc.pinyin.contains(query) || doc.acronym.contains(query) { Some(80) } else { None }
+    })
+}
+
+fn time_per_query(iterations: u32, mut f: impl FnMut() -> usize) -> (Duration, usize) {
+    let mut hits = 0;
+    let start = Instant::now();
+    for _ in 0..iterations {
+        hits = f();
+    }
+    (start.elapsed() / iterations, hits)
+}
+
+fn main() {
+    let matcher = SkimMatcherV2::default();
+
+    for &n in SIZES {
+        let docs = generate(n);
+        let start = Instant::now();
+        let index = SearchIndex::build(docs.iter().map(|d| [d.title.as_str(), d.pinyin.as_str(), d.acronym.as_str()]), 0);
+        println!("\n== {} entries (index built in {:.2?}) ==", n, start.elapsed());
+        println!("{:<10} {:>12} {:>12} {:>12} {:>10}", "query", "full scan", "indexed", "candidates", "matches");
+
+        let iterations = (10_000_000 / n).clamp(3, 200) as u32;
+        for &query in QUERIES {
+            let (full, full_hits) = time_per_query(iterations, || {
+                docs.iter().filter(|d| score(&matcher, d, query).is_some()).count()
+            });
+            let tokens = [vec![query.to_string()]];
+            let mut candidate_count = 0;
+            let (indexed, indexed_hits) = time_per_query(iterations, || {
+                let candidates = index.candidates(&tokens, MAX_RESULTS, &[], |_| true);
+                candidate_count = candidates.len();
+                candidates.iter().filter(|&&doc| score(&matcher, &docs[doc as usize], query).is_some()).count()
+            });
+            println!(
+                "{:<10} {:>12.2?} {:>12.2?} {:>12} {:>10}",
+                query, full, indexed, candidate_count, format!("{}/{}", indexed_hits, full_hits)
+            );
+        }
+    }
+}
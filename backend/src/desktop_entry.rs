@@ -0,0 +1,499 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// ==========================================
+// freedesktop Desktop Entry 解析器
+// 规范: https://specifications.freedesktop.org/desktop-entry-spec/latest/
+// ==========================================
+
+#[derive(Debug, Clone, Default)]
+pub struct DesktopEntry {
+    pub name: String,
+    pub localized_names: HashMap<String, String>, // locale -> Name[locale]
+    pub generic_name: Option<String>,
+    pub localized_generic_names: HashMap<String, String>,
+    pub comment: Option<String>,
+    pub exec: Option<String>,
+    pub try_exec: Option<String>,
+    pub icon: Option<String>,
+    pub terminal: bool,
+    pub keywords: Vec<String>,
+    pub localized_keywords: HashMap<String, Vec<String>>,
+    pub categories: Vec<String>,
+    pub no_display: bool,
+    pub hidden: bool,
+    pub only_show_in: Vec<String>,
+    pub not_show_in: Vec<String>,
+    pub entry_type: String,
+}
+
+impl DesktopEntry {
+    pub fn parse_file(path: &Path) -> Option<Self> {
+        let content = fs::read_to_string(path).ok()?;
+        Self::parse(&content)
+    }
+
+    pub fn parse(content: &str) -> Option<Self> {
+        let fields = parse_main_group(content);
+
+        let mut entry = DesktopEntry {
+            name: unescape_string(fields.get("Name")?),
+            entry_type: fields.get("Type").cloned().unwrap_or_default(),
+            ..Default::default()
+        };
+
+        let string = |key: &str| fields.get(key).map(|v| unescape_string(v));
+        let list = |key: &str| fields.get(key).map(|v| split_list(v)).unwrap_or_default();
+        let boolean = |key: &str| fields.get(key).map(|v| v == "true").unwrap_or(false);
+
+        entry.generic_name = string("GenericName");
+        entry.comment = string("Comment");
+        entry.exec = string("Exec");
+        entry.try_exec = string("TryExec");
+        entry.icon = string("Icon");
+        entry.keywords = list("Keywords");
+        entry.categories = list("Categories");
+        entry.terminal = boolean("Terminal");
+        entry.no_display = boolean("NoDisplay");
+        entry.hidden = boolean("Hidden");
+        entry.only_show_in = list("OnlyShowIn");
+        entry.not_show_in = list("NotShowIn");
+
+        // 本地化键: Name[zh_CN]=...
+        for (key, value) in &fields {
+            let Some((base, locale)) = split_localized_key(key) else { continue };
+            match base {
+                "Name" => { entry.localized_names.insert(locale.to_string(), unescape_string(value)); }
+                "GenericName" => { entry.localized_generic_names.insert(locale.to_string(), unescape_string(value)); }
+                "Keywords" => { entry.localized_keywords.insert(locale.to_string(), split_list(value)); }
+                _ => {}
+            }
+        }
+
+        Some(entry)
+    }
+
+    // 按 locale 回退链选出显示名称，都没有则使用默认 Name
+    pub fn display_name(&self, locales: &[String]) -> &str {
+        locales.iter()
+            .find_map(|l| self.localized_names.get(l))
+            .unwrap_or(&self.name)
+    }
+
+    // 所有语言版本的名称 (含默认 Name)，已去重
+    pub fn all_names(&self) -> Vec<&str> {
+        let mut names = vec![self.name.as_str()];
+        let mut localized: Vec<_> = self.localized_names.iter().collect();
+        localized.sort();
+        for (_, name) in localized {
+            if !names.contains(&name.as_str()) {
+                names.push(name);
+            }
+        }
+        names
+    }
+
+    // 所有语言版本的 GenericName + Keywords，已去重
+    pub fn all_keywords(&self) -> Vec<String> {
+        let mut keywords: Vec<String> = Vec::new();
+        let generic = self.generic_name.iter().chain(self.localized_generic_names.values());
+        let listed = self.keywords.iter().chain(self.localized_keywords.values().flatten());
+        for k in generic.chain(listed) {
+            if !keywords.contains(k) {
+                keywords.push(k.clone());
+            }
+        }
+        keywords
+    }
+
+    // 是否应该出现在启动器中：类型、隐藏标记、桌面环境限制、TryExec
+    pub fn should_show(&self, current_desktops: &[String]) -> bool {
+        if self.entry_type != "Application" || self.no_display || self.hidden {
+            return false;
+        }
+        if self.exec.as_deref().unwrap_or("").trim().is_empty() {
+            return false;
+        }
+        if !self.only_show_in.is_empty()
+            && !current_desktops.iter().any(|d| self.only_show_in.contains(d))
+        {
+            return false;
+        }
+        if current_desktops.iter().any(|d| self.not_show_in.contains(d)) {
+            return false;
+        }
+        match &self.try_exec {
+            Some(bin) => find_executable(bin).is_some(),
+            None => true,
+        }
+    }
+
+    // 展开 Exec 中的字段代码，返回可直接执行的参数列表
+    // 启动器不传递文件/URL，所以 %f %F %u %U 直接丢弃；%c 是按 locale 翻译后的名称
+    pub fn expand_exec(&self, desktop_path: &str, locales: &[String]) -> Option<Vec<String>> {
+        let args = split_exec(self.exec.as_deref()?)?;
+        let mut out = Vec::with_capacity(args.len());
+
+        for arg in args {
+            match arg.as_str() {
+                "%f" | "%F" | "%u" | "%U" | "%d" | "%D" | "%n" | "%N" | "%v" | "%m" => continue,
+                "%i" => {
+                    if let Some(icon) = &self.icon {
+                        out.push("--icon".to_string());
+                        out.push(icon.clone());
+                    }
+                    continue;
+                }
+                _ => {}
+            }
+
+            let mut expanded = String::with_capacity(arg.len());
+            let mut chars = arg.chars();
+            while let Some(c) = chars.next() {
+                if c != '%' {
+                    expanded.push(c);
+                    continue;
+                }
+                match chars.next() {
+                    Some('%') => expanded.push('%'),
+                    Some('c') => expanded.push_str(self.display_name(locales)),
+                    Some('k') => expanded.push_str(desktop_path),
+                    // 其他(包括嵌入在参数中的 %f 等)一律移除
+                    _ => {}
+                }
+            }
+            out.push(expanded);
+        }
+
+        if out.is_empty() { None } else { Some(out) }
+    }
+}
+
+// 当前桌面环境 (XDG_CURRENT_DESKTOP 为冒号分隔列表，如 "ubuntu:GNOME")
+pub fn current_desktops() -> Vec<String> {
+    std::env::var("XDG_CURRENT_DESKTOP")
+        .map(|v| v.split(':').filter(|s| !s.is_empty()).map(String::from).collect())
+        .unwrap_or_default()
+}
+
+// 当前界面语言的 locale 回退链 (LC_ALL > LC_MESSAGES > LANG)
+// 例如 "zh_CN.UTF-8" -> ["zh_CN", "zh"]，"sr_RS@latin" -> ["sr_RS@latin", "sr_RS", "sr@latin", "sr"]
+pub fn current_locales() -> Vec<String> {
+    locales_from(|var| std::env::var(var).ok())
+}
+
+fn locales_from(env: impl Fn(&str) -> Option<String>) -> Vec<String> {
+    let value = ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|var| env(var))
+        .find(|v| !v.is_empty());
+    match value {
+        Some(v) => locale_fallbacks(&v),
+        None => Vec::new(),
+    }
+}
+
+fn locale_fallbacks(locale: &str) -> Vec<String> {
+    // lang_COUNTRY.ENCODING@MODIFIER，编码部分在匹配时忽略
+    let (rest, modifier) = match locale.split_once('@') {
+        Some((r, m)) => (r, Some(m)),
+        None => (locale, None),
+    };
+    let rest = rest.split('.').next().unwrap_or(rest);
+    let (lang, country) = match rest.split_once('_') {
+        Some((l, c)) => (l, Some(c)),
+        None => (rest, None),
+    };
+    if lang.is_empty() || lang == "C" || lang == "POSIX" {
+        return Vec::new();
+    }
+
+    let mut chain = Vec::new();
+    if let (Some(c), Some(m)) = (country, modifier) {
+        chain.push(format!("{}_{}@{}", lang, c, m));
+    }
+    if let Some(c) = country {
+        chain.push(format!("{}_{}", lang, c));
+    }
+    if let Some(m) = modifier {
+        chain.push(format!("{}@{}", lang, m));
+    }
+    chain.push(lang.to_string());
+    chain
+}
+
+// ==========================================
+// 文件格式解析
+// ==========================================
+
+// "Name[zh_CN]" -> ("Name", "zh_CN")
+fn split_localized_key(key: &str) -> Option<(&str, &str)> {
+    let (base, rest) = key.split_once('[')?;
+    let locale = rest.strip_suffix(']')?;
+    Some((base, locale))
+}
+
+// 只读取 [Desktop Entry] 组，忽略 [Desktop Action xxx] 等其他组
+fn parse_main_group(content: &str) -> HashMap<String, String> {
+    let mut fields = HashMap::new();
+    let mut in_main_group = false;
+
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with('[') {
+            in_main_group = line == "[Desktop Entry]";
+            continue;
+        }
+        if !in_main_group {
+            continue;
+        }
+        if let Some((key, value)) = line.split_once('=') {
+            // 重复键以第一个为准
+            fields.entry(key.trim().to_string()).or_insert_with(|| value.trim().to_string());
+        }
+    }
+    fields
+}
+
+// 字符串类型值的转义: \s \n \t \r \\
+fn unescape_string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('s') => out.push(' '),
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('r') => out.push('\r'),
+            Some('\\') => out.push('\\'),
+            Some(other) => {
+                // 未知转义（如列表中的 \;）原样保留，交给后续处理
+                out.push('\\');
+                out.push(other);
+            }
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+// 列表类型值: 分号分隔，\; 表示字面分号
+fn split_list(value: &str) -> Vec<String> {
+    let mut items = Vec::new();
+    let mut current = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(';') => current.push(';'),
+                Some(other) => {
+                    current.push('\\');
+                    current.push(other);
+                }
+                None => current.push('\\'),
+            },
+            ';' => {
+                let item = unescape_string(current.trim());
+                if !item.is_empty() {
+                    items.push(item);
+                }
+                current.clear();
+            }
+            _ => current.push(c),
+        }
+    }
+    let item = unescape_string(current.trim());
+    if !item.is_empty() {
+        items.push(item);
+    }
+    items
+}
+
+// ==========================================
+// Exec 命令行拆分 / 拼接
+// ==========================================
+
+// 按规范拆分 Exec: 空白分隔，双引号包裹的参数内 \" \` \$ \\ 为转义
+pub fn split_exec(exec: &str) -> Option<Vec<String>> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut has_token = false;
+    let mut in_quotes = false;
+    let mut chars = exec.chars();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' => in_quotes = false,
+                '\\' => match chars.next() {
+                    Some(e @ ('"' | '`' | '$' | '\\')) => current.push(e),
+                    Some(other) => {
+                        current.push('\\');
+                        current.push(other);
+                    }
+                    None => return None,
+                },
+                _ => current.push(c),
+            }
+        } else if c.is_whitespace() {
+            if has_token {
+                args.push(std::mem::take(&mut current));
+                has_token = false;
+            }
+        } else if c == '"' {
+            in_quotes = true;
+            has_token = true;
+        } else {
+            current.push(c);
+            has_token = true;
+        }
+    }
+
+    // 引号未闭合视为非法 Exec
+    if in_quotes {
+        return None;
+    }
+    if has_token {
+        args.push(current);
+    }
+    Some(args)
+}
+
+// split_exec 的逆操作，用于把展开后的参数存入 action_data
+pub fn join_exec(args: &[String]) -> String {
+    args.iter()
+        .map(|arg| {
+            let needs_quotes = arg.is_empty()
+                || arg.chars().any(|c| c.is_whitespace() || matches!(c, '"' | '\'' | '\\' | '`' | '$'));
+            if !needs_quotes {
+                return arg.clone();
+            }
+            let mut quoted = String::with_capacity(arg.len() + 2);
+            quoted.push('"');
+            for c in arg.chars() {
+                if matches!(c, '"' | '`' | '$' | '\\') {
+                    quoted.push('\\');
+                }
+                quoted.push(c);
+            }
+            quoted.push('"');
+            quoted
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// 绝对路径直接检查，否则在 PATH 中查找 (TryExec、终端检测共用)
+pub fn find_executable(bin: &str) -> Option<PathBuf> {
+    let path = Path::new(bin);
+    if path.is_absolute() {
+        return path.is_file().then(|| path.to_path_buf());
+    }
+    let paths = std::env::var_os("PATH")?;
+    std::env::split_paths(&paths)
+        .map(|dir| dir.join(bin))
+        .find(|candidate| candidate.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn locales(vars: &[(&str, &str)]) -> Vec<String> {
+        let vars: HashMap<&str, &str> = vars.iter().copied().collect();
+        locales_from(|var| vars.get(var).map(|v| v.to_string()))
+    }
+
+    #[test]
+    fn locale_variables_in_priority_order() {
+        assert_eq!(locales(&[("LANG", "de_DE.UTF-8")]), strings(&["de_DE", "de"]));
+        assert_eq!(locales(&[("LANG", "de_DE.UTF-8"), ("LC_MESSAGES", "fr_FR")]), strings(&["fr_FR", "fr"]));
+        assert_eq!(locales(&[("LANG", "de_DE"), ("LC_MESSAGES", "fr_FR"), ("LC_ALL", "zh_CN.UTF-8")]), strings(&["zh_CN", "zh"]));
+        // 空值视为未设置
+        assert_eq!(locales(&[("LANG", "de_DE"), ("LC_ALL", "")]), strings(&["de_DE", "de"]));
+        assert_eq!(locales(&[("LANG", "C.UTF-8")]), Vec::<String>::new());
+        assert_eq!(locales(&[]), Vec::<String>::new());
+        assert_eq!(locale_fallbacks("sr_RS@latin"), strings(&["sr_RS@latin", "sr_RS", "sr@latin", "sr"]));
+    }
+
+    #[test]
+    fn localized_name_follows_fallback_chain() {
+        let entry = DesktopEntry::parse("[Desktop Entry]\nName=Files\nName[zh]=文件\nName[zh_TW]=檔案\n").unwrap();
+        assert_eq!(entry.display_name(&strings(&["zh_TW", "zh"])), "檔案");
+        assert_eq!(entry.display_name(&strings(&["zh_CN", "zh"])), "文件");
+        assert_eq!(entry.display_name(&strings(&["de"])), "Files");
+        assert_eq!(entry.all_names(), vec!["Files", "文件", "檔案"]);
+    }
+
+    #[test]
+    fn string_and_list_escapes() {
+        assert_eq!(unescape_string(r"a\sb\tc\\d\;e"), "a b\tc\\d\\;e");
+        assert_eq!(split_list(r"Text;Editor\;Viewer; ;Dev\sTools;"), strings(&["Text", "Editor;Viewer", "Dev Tools"]));
+        assert_eq!(split_list(""), Vec::<String>::new());
+    }
+
+    #[test]
+    fn exec_with_quoted_paths_round_trips() {
+        let exec = r#""/opt/My App/bin/app" --title "say \"hi\"" --cost \$5 %U"#;
+        let args = split_exec(exec).unwrap();
+        assert_eq!(args, strings(&["/opt/My App/bin/app", "--title", "say \"hi\"", "--cost", "\\$5", "%U"]));
+        assert_eq!(split_exec(&join_exec(&args)).unwrap(), args);
+        assert_eq!(join_exec(&strings(&["/usr/bin/app", "", "a b"])), r#"/usr/bin/app "" "a b""#);
+        assert_eq!(split_exec(r#"app "unterminated"#), None);
+    }
+
+    #[test]
+    fn field_codes_are_expanded_or_dropped() {
+        let entry = DesktopEntry::parse(
+            "[Desktop Entry]\nType=Application\nName=Viewer\nName[de]=Betrachter\nIcon=viewer\nExec=viewer %i --name=%c --file %f %U 100%% --from=%k\n",
+        ).unwrap();
+        assert_eq!(
+            entry.expand_exec("/usr/share/applications/viewer.desktop", &[]).unwrap(),
+            strings(&["viewer", "--icon", "viewer", "--name=Viewer", "--file", "100%", "--from=/usr/share/applications/viewer.desktop"]),
+        );
+        // %c 使用与标题相同的本地化名称
+        let args = entry.expand_exec("viewer.desktop", &strings(&["de_DE", "de"])).unwrap();
+        assert_eq!(args[3], "--name=Betrachter");
+        // 没有 Icon 时 %i 整个去掉
+        let entry = DesktopEntry::parse("[Desktop Entry]\nName=A\nExec=a %i %F\n").unwrap();
+        assert_eq!(entry.expand_exec("a.desktop", &[]).unwrap(), strings(&["a"]));
+    }
+
+    #[test]
+    fn hidden_and_desktop_restricted_entries() {
+        let parse = |extra: &str| DesktopEntry::parse(&format!("[Desktop Entry]\nType=Application\nName=A\nExec=a\n{}", extra)).unwrap();
+        let gnome = strings(&["ubuntu", "GNOME"]);
+        assert!(parse("").should_show(&gnome));
+        assert!(!parse("Hidden=true\n").should_show(&gnome));
+        assert!(!parse("NoDisplay=true\n").should_show(&gnome));
+        assert!(parse("OnlyShowIn=GNOME;Unity;\n").should_show(&gnome));
+        assert!(!parse("OnlyShowIn=KDE;\n").should_show(&gnome));
+        assert!(!parse("OnlyShowIn=KDE;\n").should_show(&[]));
+        assert!(!parse("NotShowIn=GNOME;\n").should_show(&gnome));
+        let link = DesktopEntry::parse("[Desktop Entry]\nType=Link\nName=A\nExec=a\n").unwrap();
+        assert!(!link.should_show(&gnome));
+        assert!(!parse("TryExec=/nonexistent/omnibox-test-binary\n").should_show(&gnome));
+    }
+
+    #[test]
+    fn only_main_group_is_read() {
+        let entry = DesktopEntry::parse(
+            "# comment\n[Desktop Entry]\nName=Main\nName=Second\nExec=main\n[Desktop Action new]\nName=New Window\nExec=main --new\n",
+        ).unwrap();
+        assert_eq!(entry.name, "Main");
+        assert_eq!(entry.exec.as_deref(), Some("main"));
+        assert!(DesktopEntry::parse("[Desktop Action new]\nName=X\n").is_none());
+    }
+}
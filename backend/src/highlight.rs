@@ -0,0 +1,121 @@
+use crate::models::{SearchHit, SearchResult};
+use crate::normalize;
+use crate::pinyin_match::match_syllables;
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+
+// ==========================================
+// 搜索结果高亮
+// ==========================================
+// 只对最终入选的条目计算。每个词按打分时的顺序确定它匹配的位置：
+// 1. 标题模糊匹配 -> fuzzy_indices 给出的字符 (在归一化的标题上匹配，再对应回原文的字符)
+// 2. 拼音按字对齐匹配 -> 匹配到的汉字 ("wx" / "weix" -> "微信" 两个字，多音字的每种读法都会尝试)
+// 3. 以上都没有 (别名、关键词、文件名匹配) -> 在副标题 (路径) 中找最后一处出现的位置
+
+pub type Span = (usize, usize);
+
+// tokens 为各个词的拼法 (见 Scorer::spellings)，第一种为原样
+pub fn highlight(item: SearchResult, tokens: &[Vec<String>], matcher: &SkimMatcherV2) -> SearchHit {
+    let mut title = Vec::new();
+    let mut subtitle = Vec::new();
+
+    for spellings in tokens {
+        let spans = title_spans(&item, spellings, matcher);
+        if spans.is_empty() {
+            subtitle.extend(rfind_chars(&item.subtitle, &spellings[0]));
+        } else {
+            title.extend(spans);
+        }
+    }
+
+    SearchHit {
+        score: item.score,
+        item,
+        title_highlights: merge(title),
+        subtitle_highlights: merge(subtitle),
+        score_breakdown: None,
+    }
+}
+
+fn title_spans(item: &SearchResult, spellings: &[String], matcher: &SkimMatcherV2) -> Vec<Span> {
+    let (folded, origins) = normalize::fold_indexed(&item.title);
+    if let Some((_, indices)) = matcher.fuzzy_indices(&folded, &spellings[0]) {
+        return indices.into_iter().map(|i| (origins[i], origins[i] + 1)).collect();
+    }
+
+    // 与打分一致：依次尝试每种拼法，取断开最少、其次只输入声母的读法
+    for token in spellings {
+        let best = item.pinyin_readings()
+            .filter_map(|reading| match_syllables(reading, token))
+            .min_by_key(|m| (m.gaps, !m.abbreviated));
+        if let Some(m) = best {
+            return m.chars.into_iter().map(|i| (i, i + 1)).collect();
+        }
+    }
+    Vec::new()
+}
+
+// 不区分大小写地查找 needle 在 haystack 中最后一次出现的字符区间
+fn rfind_chars(haystack: &str, needle: &str) -> Option<Span> {
+    let lower = |c: char| c.to_lowercase().next().unwrap_or(c);
+    let hay: Vec<char> = haystack.chars().map(lower).collect();
+    let needle: Vec<char> = needle.chars().map(lower).collect();
+    if needle.is_empty() || needle.len() > hay.len() {
+        return None;
+    }
+    (0..=hay.len() - needle.len())
+        .rev()
+        .find(|&i| hay[i..i + needle.len()] == needle[..])
+        .map(|i| (i, i + needle.len()))
+}
+
+// 排序并合并相邻或重叠的区间
+fn merge(mut spans: Vec<Span>) -> Vec<Span> {
+    spans.sort_unstable();
+    let mut merged: Vec<Span> = Vec::with_capacity(spans.len());
+    for (start, end) in spans {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transliterate::Transliteration;
+
+    fn item(path: &str, title: &str) -> SearchResult {
+        let (pinyin, variants) = Transliteration::new(&["zh".to_string()]).readings(title);
+        let mut item = SearchResult::new(path.into(), title.into(), "Application".into(), pinyin);
+        item.pinyin_variants = variants;
+        item
+    }
+
+    fn spans(query: &str, item: &SearchResult) -> (Vec<Span>, Vec<Span>) {
+        let tokens: Vec<Vec<String>> = query.split_whitespace().map(|t| vec![t.to_string()]).collect();
+        let hit = highlight(item.clone(), &tokens, &SkimMatcherV2::default());
+        (hit.title_highlights, hit.subtitle_highlights)
+    }
+
+    #[test]
+    fn pinyin_matches_highlight_the_chinese_characters() {
+        let wechat = item("/opt/wechat/wechat", "微信");
+        assert_eq!(spans("wx", &wechat).0, vec![(0, 2)]);
+        assert_eq!(spans("xin", &wechat).0, vec![(1, 2)]);
+        // 多音字按匹配上的读法高亮
+        assert_eq!(spans("cq", &item("/maps", "重庆地图")).0, vec![(0, 2)]);
+    }
+
+    #[test]
+    fn title_fuzzy_matches_and_subtitle_fallback() {
+        let code = item("/usr/bin/code", "Visual Studio Code");
+        assert_eq!(spans("stu code", &code), (vec![(7, 10), (14, 18)], vec![]));
+        // 标题匹配不上的词在路径中找最后一处
+        assert_eq!(spans("bin", &code), (vec![], vec![(5, 8)]));
+        // 原文中的字符位置：归一化前的全角字母
+        assert_eq!(spans("ab", &item("/x", "ＡＢ")).0, vec![(0, 2)]);
+    }
+}
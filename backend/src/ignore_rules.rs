@@ -0,0 +1,207 @@
+use ignore::gitignore::Gitignore;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+// ==========================================
+// 目录中的忽略文件 (.omniboxignore / .ignore / .gitignore)
+// ==========================================
+// 语义与 ripgrep 一致：
+// - 语法为 gitignore 语法，规则相对所在目录，"!" 可以重新包含
+// - 越深的目录优先；不同种类之间 .omniboxignore > .ignore > .gitignore
+// - .gitignore 只在 git 仓库 (某一级目录含 .git) 中生效，且不跨越仓库根目录向上生效
+
+pub const CUSTOM_IGNORE_FILE: &str = ".omniboxignore";
+pub const IGNORE_FILE_NAMES: &[&str] = &[CUSTOM_IGNORE_FILE, ".ignore", ".gitignore"];
+
+// 一个目录中存在的忽略文件及其修改时间 (用于判断增量扫描缓存是否仍然有效)
+pub type IgnoreFiles = Vec<(PathBuf, SystemTime)>;
+
+// 读取 dir 中存在的忽略文件
+pub fn ignore_files_in(dir: &Path) -> IgnoreFiles {
+    IGNORE_FILE_NAMES.iter()
+        .map(|name| dir.join(name))
+        .filter_map(|path| {
+            let mtime = std::fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+            Some((path, mtime))
+        })
+        .collect()
+}
+
+// 上次记录的忽略文件是否都没有被修改过 (增删会改变目录 mtime，这里只需检查内容修改)
+pub fn ignore_files_unchanged(files: &IgnoreFiles) -> bool {
+    files.iter().all(|(path, mtime)| {
+        std::fs::metadata(path).and_then(|m| m.modified()).ok() == Some(*mtime)
+    })
+}
+
+pub fn is_git_root(dir: &Path) -> bool {
+    dir.join(".git").exists()
+}
+
+#[derive(Default)]
+struct IgnoreLevel {
+    custom: Option<Gitignore>,
+    ignore: Option<Gitignore>,
+    git: Option<Gitignore>,
+    has_git: bool,
+}
+
+// 从根目录到当前目录的忽略规则 (浅 -> 深)，进入子目录时在末尾追加一层
+#[derive(Clone, Default)]
+pub struct IgnoreStack {
+    levels: Vec<Arc<IgnoreLevel>>,
+    // 整条规则链的指纹：任意一级的忽略文件变化都会改变它
+    stamp: u64,
+}
+
+impl IgnoreStack {
+    // 由 dir 及其所有上级目录构建 (扫描根目录、监听到的零散变化)
+    pub fn for_dir(dir: &Path) -> Self {
+        let mut ancestors: Vec<&Path> = dir.ancestors().collect();
+        ancestors.reverse();
+        ancestors.into_iter().fold(IgnoreStack::default(), |stack, dir| {
+            stack.child(&ignore_files_in(dir), is_git_root(dir))
+        })
+    }
+
+    // 进入一个子目录：files 为该目录中的忽略文件，has_git 为该目录是否为仓库根目录
+    pub fn child(&self, files: &IgnoreFiles, has_git: bool) -> Self {
+        let mut hasher = DefaultHasher::new();
+        self.stamp.hash(&mut hasher);
+        files.hash(&mut hasher);
+        has_git.hash(&mut hasher);
+
+        let mut level = IgnoreLevel { has_git, ..IgnoreLevel::default() };
+        for (path, _) in files {
+            let (matcher, err) = Gitignore::new(path);
+            if let Some(e) = err {
+                eprintln!("Invalid ignore rules in {}: {}", path.display(), e);
+            }
+            let slot = match path.file_name().and_then(|n| n.to_str()) {
+                Some(CUSTOM_IGNORE_FILE) => &mut level.custom,
+                Some(".ignore") => &mut level.ignore,
+                _ => &mut level.git,
+            };
+            *slot = Some(matcher);
+        }
+
+        let mut levels = self.levels.clone();
+        levels.push(Arc::new(level));
+        Self { levels, stamp: hasher.finish() }
+    }
+
+    pub fn stamp(&self) -> u64 {
+        self.stamp
+    }
+
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let any_git = self.levels.iter().any(|l| l.has_git);
+        let (mut custom, mut ignore, mut git) = (None, None, None);
+        let mut saw_git = false;
+
+        // 由深到浅，每种忽略文件取第一个明确的结论 (忽略或 "!" 重新包含)
+        for level in self.levels.iter().rev() {
+            if custom.is_none() {
+                custom = decide(&level.custom, path, is_dir);
+            }
+            if ignore.is_none() {
+                ignore = decide(&level.ignore, path, is_dir);
+            }
+            if any_git && !saw_git && git.is_none() {
+                git = decide(&level.git, path, is_dir);
+            }
+            saw_git = saw_git || level.has_git;
+        }
+        custom.or(ignore).or(git).unwrap_or(false)
+    }
+}
+
+fn decide(matcher: &Option<Gitignore>, path: &Path, is_dir: bool) -> Option<bool> {
+    let m = matcher.as_ref()?.matched(path, is_dir);
+    if m.is_none() { None } else { Some(m.is_ignore()) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    // 每个测试使用单独的临时目录
+    fn tree(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("omnibox-ignore-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&root);
+        for (path, content) in files {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        root
+    }
+
+    fn ignored(root: &Path, file: &str) -> bool {
+        let path = root.join(file);
+        IgnoreStack::for_dir(path.parent().unwrap()).is_ignored(&path, false)
+    }
+
+    #[test]
+    fn deeper_files_override_shallower_ones() {
+        let root = tree("depth", &[
+            (".ignore", "*.log\n"),
+            ("keep/.ignore", "!important.log\n"),
+        ]);
+        assert!(ignored(&root, "a.log"));
+        assert!(ignored(&root, "keep/other.log"));
+        assert!(!ignored(&root, "keep/important.log"));
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn custom_beats_ignore_beats_gitignore() {
+        let root = tree("kinds", &[
+            (".git/HEAD", ""),
+            (".gitignore", "*.sh\n"),
+            (".ignore", "!run.sh\nbuild.sh\n"),
+            (".omniboxignore", "!build.sh\n"),
+        ]);
+        assert!(ignored(&root, "other.sh"));
+        // .ignore 重新包含了 .gitignore 忽略的文件
+        assert!(!ignored(&root, "run.sh"));
+        // .omniboxignore 重新包含了 .ignore 忽略的文件
+        assert!(!ignored(&root, "build.sh"));
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn gitignore_needs_a_repository() {
+        let root = tree("git", &[
+            ("plain/.gitignore", "*.sh\n"),
+            ("repo/.git/HEAD", ""),
+            ("repo/.gitignore", "*.sh\n"),
+            ("outer/.git/HEAD", ""),
+            ("outer/.gitignore", "inner.sh\n"),
+            ("outer/sub/.git/HEAD", ""),
+        ]);
+        assert!(!ignored(&root, "plain/tool.sh"));
+        assert!(ignored(&root, "repo/tool.sh"));
+        assert!(ignored(&root, "outer/inner.sh"));
+        // 上级仓库的 .gitignore 不进入嵌套仓库
+        assert!(!ignored(&root, "outer/sub/inner.sh"));
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn stamp_changes_with_ignore_files() {
+        let root = tree("stamp", &[("sub/.ignore", "*.tmp\n")]);
+        let before = IgnoreStack::for_dir(&root.join("sub"));
+        assert_eq!(before.stamp(), IgnoreStack::for_dir(&root.join("sub")).stamp());
+        let files = ignore_files_in(&root.join("sub"));
+        assert!(ignore_files_unchanged(&files));
+
+        fs::write(root.join(".ignore"), "*.bak\n").unwrap();
+        assert_ne!(before.stamp(), IgnoreStack::for_dir(&root.join("sub")).stamp());
+        let _ = fs::remove_dir_all(&root);
+    }
+}
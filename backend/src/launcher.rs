@@ -0,0 +1,151 @@
+use crate::desktop_entry;
+use std::io;
+use std::process::{Command, Stdio};
+
+// ==========================================
+// 根据 action_type 启动条目
+// ==========================================
+// - "app": action_data 是完整命令行 (如 .desktop 的 Exec)，直接拉起进程
+// - "command": 命令行工具，放到终端里运行 (terminal 为用户配置的终端模板)
+// - 其他 ("file" / "folder")：交给系统默认程序打开 action_data
+pub fn launch(action_type: &str, action_data: &str, terminal: &str) -> io::Result<()> {
+    match action_type {
+        "app" => spawn_args(&parse_command_line(action_data)?),
+        "command" => spawn_in_terminal(action_data, terminal),
+        _ => open::that_detached(action_data),
+    }
+}
+
+fn parse_command_line(command_line: &str) -> io::Result<Vec<String>> {
+    desktop_entry::split_exec(command_line)
+        .filter(|args| !args.is_empty())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("Invalid command line: {}", command_line)))
+}
+
+fn spawn_in_terminal(command_line: &str, terminal: &str) -> io::Result<()> {
+    let command = parse_command_line(command_line)?;
+    let args = if terminal.trim().is_empty() {
+        default_terminal_args(command)?
+    } else {
+        fill_template(parse_command_line(terminal)?, command)
+    };
+    spawn_args(&args)
+}
+
+// 模板中的 "{}" 替换为命令参数；没有占位符则追加到末尾
+fn fill_template(template: Vec<String>, command: Vec<String>) -> Vec<String> {
+    let mut args = Vec::with_capacity(template.len() + command.len());
+    let mut substituted = false;
+    for part in template {
+        if part == "{}" {
+            args.extend(command.iter().cloned());
+            substituted = true;
+        } else {
+            args.push(part);
+        }
+    }
+    if !substituted {
+        args.extend(command);
+    }
+    args
+}
+
+// 未配置终端时的默认选择
+#[cfg(not(target_os = "macos"))]
+fn default_terminal_args(command: Vec<String>) -> io::Result<Vec<String>> {
+    let template = default_terminal()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No terminal emulator found"))?;
+    Ok(fill_template(template, command))
+}
+
+// Terminal.app 不接受命令参数 (open -a 会把它们当作要打开的文件)，
+// 只能通过 AppleScript 的 do script 交给它一整条 shell 命令
+#[cfg(target_os = "macos")]
+fn default_terminal_args(command: Vec<String>) -> io::Result<Vec<String>> {
+    Ok(vec!["osascript".to_string(), "-e".to_string(), terminal_app_script(&command)])
+}
+
+#[cfg(any(target_os = "macos", test))]
+fn terminal_app_script(command: &[String]) -> String {
+    // 先按 POSIX shell 单引号转义拼成命令行，再整体作为 AppleScript 字符串字面量
+    let shell_line = command.iter()
+        .map(|arg| format!("'{}'", arg.replace('\'', r"'\''")))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let literal = shell_line.replace('\\', r"\\").replace('"', r#"\""#);
+    format!("tell application \"Terminal\" to do script \"{}\"", literal)
+}
+
+#[cfg(target_os = "windows")]
+fn default_terminal() -> Option<Vec<String>> {
+    Some(["cmd", "/C", "start", "", "cmd", "/K", "{}"].iter().map(|s| s.to_string()).collect())
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn default_terminal() -> Option<Vec<String>> {
+    // 优先 $TERMINAL，其次 Debian 系的 x-terminal-emulator，最后逐个探测常见终端
+    if let Ok(term) = std::env::var("TERMINAL") {
+        if !term.trim().is_empty() {
+            return Some(vec![term, "-e".to_string(), "{}".to_string()]);
+        }
+    }
+    const CANDIDATES: &[(&str, &str)] = &[
+        ("x-terminal-emulator", "-e"),
+        ("gnome-terminal", "--"),
+        ("konsole", "-e"),
+        ("xfce4-terminal", "-x"),
+        ("alacritty", "-e"),
+        ("kitty", "--"),
+        ("wezterm", "start"),
+        ("xterm", "-e"),
+    ];
+    CANDIDATES.iter()
+        .find(|(bin, _)| desktop_entry::find_executable(bin).is_some())
+        .map(|(bin, flag)| vec![bin.to_string(), flag.to_string(), "{}".to_string()])
+}
+
+fn spawn_args(args: &[String]) -> io::Result<()> {
+    let mut child = Command::new(&args[0])
+        .args(&args[1..])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+
+    // 回收子进程，避免僵尸进程
+    std::thread::spawn(move || {
+        let _ = child.wait();
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn command_replaces_the_placeholder_or_is_appended() {
+        let command = strings(&["/usr/bin/htop", "-d", "10"]);
+        assert_eq!(
+            fill_template(strings(&["kitty", "--hold", "{}"]), command.clone()),
+            strings(&["kitty", "--hold", "/usr/bin/htop", "-d", "10"]),
+        );
+        assert_eq!(
+            fill_template(strings(&["xterm", "-e"]), command),
+            strings(&["xterm", "-e", "/usr/bin/htop", "-d", "10"]),
+        );
+    }
+
+    #[test]
+    fn terminal_app_script_quotes_for_shell_and_applescript() {
+        let script = terminal_app_script(&strings(&["/opt/My Tools/bin/run", "it's", r#"say "hi" \ bye"#]));
+        assert_eq!(
+            script,
+            r#"tell application "Terminal" to do script "'/opt/My Tools/bin/run' 'it'\\''s' 'say \"hi\" \\ bye'""#,
+        );
+    }
+}
@@ -3,161 +3,160 @@
     windows_subsystem = "windows"
 )]
 
+mod desktop_entry;
+mod highlight;
+mod ignore_rules;
+mod launcher;
 mod models;
+mod normalize;
+mod pinyin_input;
+mod pinyin_match;
+mod query;
+mod ranking;
+mod scan_job;
 mod scanner;
+mod search_index;
+mod shortcut;
+mod state;
 mod storage;
-
-use crate::models::{AppSettings, SearchResult, UserHabits};
+mod transliterate;
+mod watcher;
+
+use crate::models::{AppSettings, ScanProgressEvent, ScoreBreakdown, SearchHit, UsageStat};
+use crate::query::QueryError;
+use crate::ranking::Scorer;
+use crate::scan_job::{ScanJobs, ScanProgress};
+use crate::scanner::ScanPlan;
+use crate::search_index::TopK;
+use crate::state::AppState;
 use crate::storage::Storage;
+use crate::watcher::WatchHandle;
+use arc_swap::ArcSwap;
 use auto_launch::AutoLaunchBuilder;
-use fuzzy_matcher::skim::SkimMatcherV2;
-use fuzzy_matcher::FuzzyMatcher;
-use once_cell::sync::Lazy;
-use std::path::Path;
-use std::sync::{Arc, Mutex};
+use once_cell::sync::{Lazy, OnceCell};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
 use tauri::{
-    CustomMenuItem, GlobalShortcutManager, Manager, SystemTray, SystemTrayEvent, SystemTrayMenu,
-    Window,
+    AppHandle, CustomMenuItem, GlobalShortcutManager, Manager, SystemTray, SystemTrayEvent,
+    SystemTrayMenu, Window,
 };
 
-struct AppState {
-    apps: Vec<SearchResult>,
-    habits: UserHabits,
-    settings: AppSettings,
-    storage: Storage,
-}
+// 索引快照 + 使用统计/设置，见 state.rs
+static APP_STATE: Lazy<AppState> = Lazy::new(|| AppState::load(Storage::new()));
 
-static APP_STATE: Lazy<Arc<Mutex<AppState>>> = Lazy::new(|| {
-    let storage = Storage::new();
-    let apps = storage.load_apps();
-    let habits = storage.load_habits();
-    let settings = storage.load_settings();
-
-    Arc::new(Mutex::new(AppState {
-        apps,
-        habits,
-        settings,
-        storage,
-    }))
-});
-
-// 辅助函数：安全获取锁（防止 PoisonError 导致崩溃）
-fn get_state_lock() -> std::sync::MutexGuard<'static, AppState> {
-    match APP_STATE.lock() {
-        Ok(guard) => guard,
-        Err(poisoned) => {
-            eprintln!("Warning: Mutex was poisoned. Recovering...");
-            poisoned.into_inner()
-        }
-    }
-}
+// 全量扫描任务 (同一时间最多一个)
+static SCAN_JOBS: Lazy<ScanJobs> = Lazy::new(ScanJobs::default);
+
+// 文件监听：增量更新用的扫描计划 (展开 @registry / @drives 较慢，只在扫描配置变化时重建) 与监听线程的句柄
+static WATCH_PLAN: Lazy<ArcSwap<ScanPlan>> = Lazy::new(|| ArcSwap::from_pointee(ScanPlan::new(&APP_STATE.settings())));
+static WATCHER: OnceCell<WatchHandle> = OnceCell::new();
+
+// 扫描进度事件的发送间隔
+const SCAN_PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
 
 // ==========================================
 // 智能搜索算法
 // ==========================================
 #[tauri::command]
-fn search(query: String) -> Vec<SearchResult> {
-    let state = get_state_lock();
-    let query = query.trim().to_lowercase();
-    let max_results = state.settings.max_results;
-
-    // 1. 空搜索：返回最常用的
-    if query.is_empty() {
-        let mut recent: Vec<SearchResult> = state
-            .apps
-            .iter()
-            .filter(|a| a.use_count > 0)
-            .cloned()
-            .collect();
-        // 按使用次数降序
-        recent.sort_by(|a, b| b.use_count.cmp(&a.use_count));
-        return recent.into_iter().take(max_results).collect();
-    }
+fn search(query: String) -> Result<Vec<SearchHit>, QueryError> {
+    run_search(&query, false)
+}
 
-    let matcher = SkimMatcherV2::default();
-    
-    let mut results: Vec<SearchResult> = state
-        .apps
-        .iter()
-        .filter_map(|item| {
-            let mut score = 0i64;
-            let mut matched = false;
-
-            // A. 基础 Fuzzy 匹配 (英文)
-            // ----------------------------------------------------
-            if let Some(fuzzy_score) = matcher.fuzzy_match(&item.title, &query) {
-                score += fuzzy_score;
-                matched = true;
-            }
+// 与 search 相同，但每个结果附带各项得分 (ScoreBreakdown)，用于排查排序问题和调整权重
+#[tauri::command]
+fn explain_search(query: String) -> Result<Vec<SearchHit>, QueryError> {
+    run_search(&query, true)
+}
 
-            // B. 中文拼音 / 首字母缩写匹配
-            // ----------------------------------------------------
-            if !matched || score < 50 {
-                // 如果标题拼音包含查询 (e.g., "weixin" contains "wx")
-                if item.title_pinyin.contains(&query) {
-                    score += 80;
-                    matched = true;
-                } 
-                // 或者首字母包含 (e.g., "wx" contains "wx")
-                else if item.title_acronym.contains(&query) {
-                    score += 100;
-                    matched = true;
+fn run_search(query: &str, explain: bool) -> Result<Vec<SearchHit>, QueryError> {
+    // 拆分自由文本与过滤条件 (type:script、ext:sh、-uninstall 等，见 query.rs)
+    let parsed = query::parse(query)?;
+    let now = chrono::Utc::now();
+
+    // 读取当前的索引快照与使用统计 (都不持有锁，扫描和写盘期间照常搜索)
+    let snapshot = APP_STATE.snapshot();
+    let user = APP_STATE.user();
+    let apps = &snapshot.apps;
+    let pinned = snapshot.pinned();
+    let query = parsed.text.trim();
+    let max_results = user.settings.max_results;
+
+    // 只有用过的条目 (pinned) 才有使用统计，其余条目不必查表
+    let usage = |doc: u32| -> &UsageStat {
+        if pinned.binary_search(&doc).is_ok() {
+            user.usage.get(&apps[doc as usize].id)
+        } else {
+            UsageStat::unused()
+        }
+    };
+    let passes_filters = |doc: u32| -> bool {
+        !parsed.has_filters() || parsed.matches(&apps[doc as usize], usage(doc), now)
+    };
+
+    let scorer = Scorer::new(query, &user.habits, &user.settings, now);
+
+    // 按文档号打分，只克隆最终入选的条目 (快照中的使用统计可能已过时，以最新的为准)，
+    // 并计算它们的高亮位置；explain 时重新计算入选条目的各项得分
+    let collect_top = |top: TopK| -> Vec<SearchHit> {
+        top.into_sorted()
+            .into_iter()
+            .map(|(score, doc)| {
+                let mut item = apps[doc as usize].clone();
+                let stat = user.usage.get(&item.id);
+                item.use_count = stat.use_count;
+                item.last_used = stat.last_used;
+                item.score = score;
+                let mut hit = highlight::highlight(item, scorer.spellings(), scorer.matcher());
+                if explain {
+                    hit.score_breakdown = if query.is_empty() {
+                        Some(ScoreBreakdown { frecency: score, ..ScoreBreakdown::default() })
+                    } else {
+                        scorer.score(&apps[doc as usize], usage(doc))
+                    };
                 }
-            }
-
-            if !matched {
-                return None;
-            }
-
-            // C. 智能加权 (Heuristics)
-            // ----------------------------------------------------
-            let title_lower = item.title.to_lowercase();
+                hit
+            })
+            .collect()
+    };
 
-            // 1. 完全匹配奖励 (Exact Match)
-            if title_lower == query || item.title_acronym == query {
-                score += 1000;
-            }
-            // 2. 前缀匹配奖励 (Starts With) - "code" 匹配 "Code.exe" 优于 "VS Code"
-            else if title_lower.starts_with(&query) || item.title_pinyin.starts_with(&query) {
-                score += 200;
+    // 1. 空搜索：返回最近常用的 (用过的条目就是快照中的 pinned 列表)；
+    //    只有过滤条件时 (如 "ext:sh") 在所有条目中筛选
+    if query.is_empty() {
+        let mut top = TopK::new(max_results);
+        if parsed.has_filters() {
+            for doc in (0..apps.len() as u32).filter(|&doc| passes_filters(doc)) {
+                top.push(scorer.frecency(usage(doc)), doc);
             }
-
-            // 3. 历史记录权重 (最重要!)
-            let habit_score = state.habits.get_weight(&query, &item.id);
-            score += habit_score;
-
-            // 4. 通用热度加成
-            score += (item.use_count as i64) * 20;
-
-            // 5. 长度惩罚 (Length Penalty)
-            // 名字越短通常越精确。 "Calc" 比 "OpenOffice Calc" 更好。
-            score -= item.title.len() as i64 * 2;
-
-            // 6. 路径/文件名 兜底匹配
-            // 如果标题没匹配上，但文件名匹配上了 (e.g. 标题是"微信", 搜"WeChat.exe")
-            let path = Path::new(&item.id);
-            if let Some(filename) = path.file_name().map(|n| n.to_string_lossy().to_lowercase()) {
-                 if filename.contains(&query) {
-                     score += 50;
-                 }
+        } else {
+            for &doc in &pinned {
+                top.push(scorer.frecency(usage(doc)), doc);
             }
+        }
+        // 按 frecency 降序
+        return Ok(collect_top(top));
+    }
 
-            let mut new_item = item.clone();
-            new_item.score = score;
-            Some(new_item)
-        })
-        .collect();
-
-    // D. 排序：分数高在前
-    results.sort_by(|a, b| b.score.cmp(&a.score));
-
-    results.into_iter().take(max_results).collect()
+    // 2. 先用索引筛出通过过滤条件的候选，只对候选打分 (见 ranking.rs)
+    let candidates = snapshot.index.candidates(scorer.spellings(), max_results, &pinned, passes_filters);
+    let scored = candidates
+        .into_iter()
+        .filter_map(|doc| {
+            let breakdown = scorer.score(&apps[doc as usize], usage(doc))?;
+            Some((breakdown.total(), doc))
+        });
+
+    // 3. 排序：只保留分数最高的 max_results 个
+    let mut top = TopK::new(max_results);
+    for (score, doc) in scored {
+        top.push(score, doc);
+    }
+    Ok(collect_top(top))
 }
 
 #[tauri::command]
 fn get_settings() -> AppSettings {
-    let state = get_state_lock();
-    state.settings.clone()
+    APP_STATE.settings()
 }
 
 fn handle_autostart(enable: bool) {
@@ -191,76 +190,116 @@ fn handle_autostart(enable: bool) {
 }
 
 #[tauri::command]
-async fn save_settings(new_settings: AppSettings) -> Result<(), String> {
+async fn save_settings(app: AppHandle, new_settings: AppSettings) -> Result<(), String> {
     handle_autostart(new_settings.enable_autostart);
 
-    let mut state = get_state_lock();
-    state.settings = new_settings;
-    state.storage.save_settings(&state.settings);
+    let rescan = APP_STATE.set_settings(new_settings);
+
+    // 扫描规则变化后立即重新扫描，监听的目录随之更新
+    if rescan {
+        let plan = ScanPlan::new(&APP_STATE.settings());
+        if let Some(watcher) = WATCHER.get() {
+            watcher.set_roots(plan.watch_roots());
+        }
+        WATCH_PLAN.store(Arc::new(plan));
+        refresh_index(app);
+    }
     
     Ok(())
 }
 
 #[tauri::command]
 fn execute_item(id: String, query: String) {
-    // 1. 更新内存状态 (快速)
-    {
-        let mut state = get_state_lock();
-        if !query.trim().is_empty() {
-            state.habits.record(&query, &id);
-            state.storage.save_habits(&state.habits);
-        }
-        
-        if let Some(item) = state.apps.iter_mut().find(|a| a.id == id) {
-            item.use_count += 1;
-            item.last_used = Some(chrono::Utc::now());
-        }
-    }
-
-    // 2. 异步执行和重写应用缓存 (慢速)
-    let id_clone = id.clone();
+    // 1. 更新内存中的使用统计 (快速)
+    let terminal = APP_STATE.user().settings.terminal.clone();
+    // 习惯按自由文本记录 (与搜索时查找习惯用的文本一致)
+    let habit_query = query::parse(&query).map(|q| q.text).unwrap_or(query);
+    let (action_type, action_data) = APP_STATE.record_use(&id, &habit_query)
+        .unwrap_or_else(|| ("file".to_string(), id.clone()));
+
+    // 2. 异步执行和保存使用统计 (慢速)
     std::thread::spawn(move || {
-        if let Err(e) = open::that_detached(&id_clone) {
+        if let Err(e) = launcher::launch(&action_type, &action_data, &terminal) {
             eprintln!("Failed to open item: {}", e);
         }
-        // 更新缓存中的 use_count
-        let state = get_state_lock();
-        state.storage.save_apps(&state.apps);
+        APP_STATE.save_usage();
     });
 }
 
+// 文件监听回调：把变化应用到当前索引 (识别与去重期间搜索照常使用旧快照)
+fn apply_fs_changes(paths: Vec<std::path::PathBuf>) {
+    let plan = WATCH_PLAN.load_full();
+    let updated = APP_STATE.update_apps(|apps| scanner::apply_changes(&plan, apps.to_vec(), &paths));
+    if let Some((before, after)) = updated {
+        println!("Applied {} file changes. Apps: {} -> {}.", paths.len(), before, after);
+    }
+}
+
 #[tauri::command]
-fn refresh_index() {
-    std::thread::spawn(|| {
-        println!("Starting background scan...");
-        let start = std::time::Instant::now();
-        
-        // 耗时扫描 (无锁)
-        let new_apps = scanner::scan_applications();
-        let duration = start.elapsed();
-        
-        // 合并数据 (有锁)
-        let mut state = get_state_lock();
-        
-        // 保留旧数据的统计信息
-        let old_stats: std::collections::HashMap<String, (u32, Option<chrono::DateTime<chrono::Utc>>)> = 
-            state.apps.iter()
-                .map(|a| (a.id.clone(), (a.use_count, a.last_used)))
-                .collect();
-
-        let mut merged_apps = new_apps;
-        for app in &mut merged_apps {
-            if let Some((count, last_used)) = old_stats.get(&app.id) {
-                app.use_count = *count;
-                app.last_used = *last_used;
-            }
+fn refresh_index(app: AppHandle) {
+    let Some(mut progress) = SCAN_JOBS.begin() else {
+        println!("Scan already running, queued a rescan.");
+        return;
+    };
+    std::thread::spawn(move || loop {
+        run_scan(&app, &progress);
+        // 扫描期间又收到了刷新请求 (如修改了扫描规则)，在同一线程中接着扫一次
+        match SCAN_JOBS.finish() {
+            Some(next) => progress = next,
+            None => break,
         }
+    });
+}
 
-        state.apps = merged_apps;
-        state.storage.save_apps(&state.apps);
-        
-        println!("Index refreshed in {:.2?}. Found {} apps.", duration, state.apps.len());
+// 执行一次全量扫描，并通过事件向前端报告进度与结果：
+// scan-started -> scan-progress (定时) -> scan-finished (ScanSummary)
+fn run_scan(app: &AppHandle, progress: &ScanProgress) {
+    println!("Starting background scan...");
+    let _ = app.emit_all("scan-started", ());
+
+    // 耗时扫描 (无锁)：读取上次的目录记录，只重新读取 mtime 变化过的目录
+    let storage = APP_STATE.storage();
+    let plan = scanner::ScanPlan::new(&APP_STATE.settings());
+    let scan_cache = storage.load_scan_cache();
+
+    let done = AtomicBool::new(false);
+    let (new_apps, new_cache, summary) = std::thread::scope(|s| {
+        s.spawn(|| {
+            while !done.load(Ordering::Relaxed) {
+                let _ = app.emit_all("scan-progress", progress.snapshot());
+                std::thread::sleep(SCAN_PROGRESS_INTERVAL);
+            }
+        });
+        let result = scanner::scan_applications(&plan, &scan_cache, progress);
+        done.store(true, Ordering::Relaxed);
+        result
     });
+    drop(scan_cache);
+
+    if summary.cancelled {
+        println!("Scan cancelled after {} ms.", summary.duration_ms);
+    } else {
+        storage.save_scan_cache(&new_cache);
+
+        // 建立索引后整体替换快照 (使用统计单独保存，不需要合并)
+        let count = APP_STATE.replace_apps(new_apps);
+        println!("Index refreshed in {} ms. Found {} apps.", summary.duration_ms, count);
+    }
+
+    let _ = app.emit_all("scan-progress", progress.snapshot());
+    let _ = app.emit_all("scan-finished", summary);
+}
+
+// 取消正在进行的扫描 (已有索引保持不变)；没有扫描在进行时返回 false
+#[tauri::command]
+fn cancel_scan() -> bool {
+    SCAN_JOBS.cancel()
+}
+
+// 前端打开时查询是否有扫描正在进行
+#[tauri::command]
+fn get_scan_status() -> Option<ScanProgressEvent> {
+    SCAN_JOBS.current_progress()
 }
 
 #[tauri::command]
@@ -300,8 +339,11 @@ fn main() {
         })
         .invoke_handler(tauri::generate_handler![
             search,
+            explain_search,
             execute_item,
             refresh_index,
+            cancel_scan,
+            get_scan_status,
             quit_app,
             get_settings,
             save_settings
@@ -325,14 +367,14 @@ fn main() {
             });
             
             // 启动时自动扫描
-            refresh_index();
+            refresh_index(app.handle());
+
+            // 之后通过文件监听保持索引最新
+            let _ = WATCHER.set(watcher::spawn(WATCH_PLAN.load().watch_roots(), apply_fs_changes));
             
             // 延时处理自启动
             std::thread::spawn(move || {
-                let state = get_state_lock();
-                let enable = state.settings.enable_autostart;
-                drop(state);
-                handle_autostart(enable);
+                handle_autostart(APP_STATE.settings().enable_autostart);
             });
 
             Ok(())
@@ -1,6 +1,9 @@
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 use std::collections::HashMap;
 use chrono::{DateTime, Utc};
+use std::path::PathBuf;
+use std::time::SystemTime;
 
 // --- App 数据模型 ---
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -10,10 +13,25 @@ pub struct SearchResult {
     pub subtitle: String,   // 副标题(路径)
     
     // --- 搜索优化字段 ---
-    pub title_pinyin: String,   // 全拼: "weixin"
-    pub title_acronym: String,  // 首字母: "wx"
+    pub normalized_title: String, // 归一化的标题 (见 normalize.rs)，匹配时代替 title: "Café" -> "cafe"
+    pub pinyin: PinyinReading,  // 每个字取最常用读音: "weixin" / "wx"
+    // 多音字的其他读音组合："重庆" 的 pinyin 为 "zhongqing"，这里有 "chongqing"
+    pub pinyin_variants: Vec<PinyinReading>,
+    pub keywords: Vec<String>,  // 额外关键词 (GenericName / Keywords)
+    pub aliases: Vec<Alias>,    // 别名 (其他语言的名称等)，同样参与搜索
     // -------------------
 
+    pub description: Option<String>, // 说明 (Desktop Entry 的 Comment)
+    pub icon: Option<String>,        // 图标名或图标路径
+    pub categories: Vec<String>,
+
+    // --- 快捷方式解析结果 (.lnk) ---
+    pub target: Option<String>,      // 真实目标路径，用于去重
+    pub arguments: Option<String>,
+    pub working_dir: Option<String>,
+    // 去重时合并进来的其他条目 (id)，其中任何一个变化时整组重新识别 (见 scanner::apply_changes)
+    pub duplicates: Vec<String>,
+
     #[serde(skip)]
     pub score: i64,         // 动态计算的分数
     pub action_type: String,
@@ -25,9 +43,10 @@ pub struct SearchResult {
 
 impl SearchResult {
     // 构造函数更新，传入预计算的拼音
-    pub fn new(path: String, name: String, f_type: String, pinyin: String, acronym: String) -> Self {
+    pub fn new(path: String, name: String, f_type: String, pinyin: PinyinReading) -> Self {
         Self {
             id: path.clone(),
+            normalized_title: crate::normalize::fold(&name),
             title: name,
             subtitle: path.clone(),
             score: 0,
@@ -36,45 +55,544 @@ impl SearchResult {
             use_count: 0,
             last_used: None,
             file_type: f_type,
-            title_pinyin: pinyin,
-            title_acronym: acronym,
+            pinyin,
+            pinyin_variants: Vec::new(),
+            keywords: Vec::new(),
+            aliases: Vec::new(),
+            description: None,
+            icon: None,
+            categories: Vec::new(),
+            target: None,
+            arguments: None,
+            working_dir: None,
+            duplicates: Vec::new(),
+        }
+    }
+
+    // 参与搜索匹配的所有文本，用于建立搜索索引
+    pub fn search_fields(&self) -> Vec<&str> {
+        let mut fields = vec![self.title.as_str()];
+        for reading in self.pinyin_readings() {
+            fields.extend([reading.full.as_str(), reading.acronym.as_str()]);
+        }
+        for alias in &self.aliases {
+            fields.push(alias.text.as_str());
+            for reading in alias.pinyin_readings() {
+                fields.extend([reading.full.as_str(), reading.acronym.as_str()]);
+            }
         }
+        fields.extend(self.keywords.iter().map(String::as_str));
+        fields.push(self.file_name());
+        fields
+    }
+
+    // 标题的所有拼音读法，最常用的读音在前
+    pub fn pinyin_readings(&self) -> impl Iterator<Item = &PinyinReading> + Clone {
+        std::iter::once(&self.pinyin).chain(&self.pinyin_variants)
+    }
+
+    // 路径中的文件名 (如 "WeChat.exe")，标题匹配不上时作为兜底
+    pub fn file_name(&self) -> &str {
+        std::path::Path::new(&self.id).file_name().and_then(|n| n.to_str()).unwrap_or("")
+    }
+
+    // 条目指向的程序或文件的路径：快捷方式为解析出的目标，.desktop / 命令为命令行中的程序 (绝对路径时)，
+    // 其他条目以及取不到时为自身路径 (id)。in: 过滤与路径匹配使用它
+    pub fn target_path(&self) -> Cow<'_, str> {
+        if let Some(target) = &self.target {
+            return Cow::Borrowed(target);
+        }
+        if matches!(self.action_type.as_str(), "app" | "command") {
+            let program = crate::desktop_entry::split_exec(&self.action_data).and_then(|args| args.into_iter().next());
+            if let Some(program) = program.filter(|p| std::path::Path::new(p).is_absolute()) {
+                return Cow::Owned(program);
+            }
+        }
+        Cow::Borrowed(&self.id)
+    }
+}
+
+// 搜索接口返回的结果：条目本身 + 匹配高亮位置
+// 高亮为 [start, end) 字符区间 (按 Unicode 字符计数，不是字节)，拼音/首字母匹配会映射回对应的汉字
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchHit {
+    #[serde(flatten)]
+    pub item: SearchResult,
+    pub score: i64,
+    pub title_highlights: Vec<(usize, usize)>,
+    pub subtitle_highlights: Vec<(usize, usize)>,
+    // 只有 explain_search 会填写
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score_breakdown: Option<ScoreBreakdown>,
+}
+
+// 得分的各个组成部分 (见 ranking.rs)，相加即为总分
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct ScoreBreakdown {
+    pub fuzzy: i64,          // 标题模糊匹配 (各词之和)
+    pub pinyin: i64,         // 全拼匹配 +80 (断开扣分)
+    pub acronym: i64,        // 首字母匹配 +100 (断开扣分)
+    pub alias: i64,          // 别名匹配
+    pub keyword: i64,        // 关键词包含 +60
+    pub file_name: i64,      // 文件名包含 +50
+    pub path: i64,           // 其他方式都匹配不上时，路径包含 +30
+    pub word_boundary: i64,  // 词首匹配 +40
+    pub exact: i64,          // 完全匹配 +1000
+    pub prefix: i64,         // 前缀匹配 +200
+    pub habit: i64,          // 该查询下的历史选择 x1000
+    pub frecency: i64,       // 随时间衰减的使用频率 x20
+    pub length_penalty: i64, // 标题长度 x-2
+}
+
+impl ScoreBreakdown {
+    pub fn total(&self) -> i64 {
+        self.fuzzy + self.pinyin + self.acronym + self.alias + self.keyword + self.file_name + self.path
+            + self.word_boundary + self.exact + self.prefix + self.habit + self.frecency + self.length_penalty
+    }
+}
+
+// 一种读法 (拼音或罗马字，见 transliterate.rs)。syllables 记录全拼中每个字符对应的片段长度 (字节)，
+// 匹配时按它对齐到字的边界 (见 pinyin_match.rs)："微信" -> "weixin"、"wx"、[3, 3]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PinyinReading {
+    pub full: String,
+    pub acronym: String,
+    pub syllables: Vec<u8>,
+}
+
+// 别名：不用于显示，但和标题一样预计算拼音
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Alias {
+    pub text: String,
+    pub pinyin: PinyinReading,
+    pub pinyin_variants: Vec<PinyinReading>, // 多音字的其他读音组合，同 SearchResult
+}
+
+impl Alias {
+    pub fn pinyin_readings(&self) -> impl Iterator<Item = &PinyinReading> + Clone {
+        std::iter::once(&self.pinyin).chain(&self.pinyin_variants)
+    }
+}
+
+// --- 增量扫描缓存 ---
+// 目录的 mtime 只在其直接子项增删/改名时变化，mtime 未变的目录无需重新读取，
+// 直接复用上次扫描的结果，只需继续检查子目录
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct ScanCache {
+    // 影响扫描结果的环境 (语言、桌面环境等)，变化时整个缓存作废
+    pub fingerprint: String,
+    pub dirs: HashMap<PathBuf, DirRecord>,
+}
+
+// 目录 mtime 未变化时直接复用 items。目录 mtime 只在增删、重命名文件时变化：
+// - 编辑 .desktop / .lnk 的内容不会改变它，这些文件的修改时间单独记录在 entry_files 中
+// - chmod +x 也不会改变它 (文件 mtime 同样不变)，PATH 目录中新变为可执行的命令运行期间由文件监听收录，
+//   程序未运行时的修改要等目录有其他变化才会收录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DirRecord {
+    pub mtime: SystemTime,
+    pub with_commands: bool,       // 是否按 PATH 目录的规则收录了无扩展名命令
+    pub items: Vec<SearchResult>,  // 该目录下直接包含的条目 (去重前)
+    // 按内容解析的入口文件 (.desktop / .lnk，包括解析后被隐藏的) 及修改时间
+    pub entry_files: Vec<(PathBuf, SystemTime)>,
+    pub subdirs: Vec<PathBuf>,     // 需要继续扫描的子目录 (已过滤黑名单/隐藏目录)
+    // 该目录中的忽略文件及修改时间；忽略文件被编辑时目录 mtime 不变，需要单独检查
+    pub ignore_files: Vec<(PathBuf, SystemTime)>,
+    pub git_root: bool,            // 目录中是否有 .git
+    pub ignore_stamp: u64,         // 读取时生效的整条忽略规则链 (含上级目录) 的指纹
+}
+
+// --- 扫描任务事件 (发送给前端) ---
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct ScanProgressEvent {
+    pub roots_total: usize,
+    pub roots_completed: usize,
+    pub dirs_visited: usize,
+    pub files_visited: usize,  // 实际从磁盘读取的目录项 (缓存命中的目录不计)
+    pub items_found: usize,    // 去重前的条目数
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct RootScanSummary {
+    pub path: PathBuf,
+    pub items: usize,
+    pub dirs: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct ScanSummary {
+    pub cancelled: bool,
+    pub duration_ms: u64,
+    pub total_items: usize,           // 去重后的条目数
+    pub roots: Vec<RootScanSummary>,
+    pub error_count: usize,
+    pub errors: Vec<String>,          // 只保留前若干条
+}
+
+// --- 使用统计 ---
+// 与条目列表分开保存：执行条目只需改写这一小份数据，不必重写整个 apps_cache
+// 每个条目保留最近 MAX_RECENT_VISITS 次使用的时间，用于计算 frecency
+const MAX_RECENT_VISITS: usize = 10;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct UsageStat {
+    pub use_count: u32,
+    pub last_used: Option<DateTime<Utc>>,
+    pub recent_visits: Vec<DateTime<Utc>>, // 最近若干次使用的时间 (旧 -> 新)
+}
+
+static NO_USAGE: UsageStat = UsageStat { use_count: 0, last_used: None, recent_visits: Vec::new() };
+
+impl UsageStat {
+    // 没有使用记录的条目
+    pub fn unused() -> &'static UsageStat {
+        &NO_USAGE
+    }
+
+    // frecency = 使用次数 x 最近几次使用的平均衰减权重 (每经过一个半衰期权重减半)。
+    // 去年用了 500 次、之后再没打开的程序，会逐渐排到这周每天都在用的程序后面
+    pub fn frecency(&self, now: DateTime<Utc>, half_life_days: f64) -> f64 {
+        if half_life_days <= 0.0 {
+            return self.use_count as f64;
+        }
+        let samples: &[DateTime<Utc>] = if self.recent_visits.is_empty() {
+            self.last_used.as_slice()
+        } else {
+            &self.recent_visits
+        };
+        if samples.is_empty() {
+            return 0.0;
+        }
+        let weights: f64 = samples.iter()
+            .map(|t| {
+                let age_days = (now - *t).num_seconds().max(0) as f64 / 86400.0;
+                0.5f64.powf(age_days / half_life_days)
+            })
+            .sum();
+        self.use_count as f64 * weights / samples.len() as f64
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct UsageStats {
+    // Key: AppID
+    pub by_id: HashMap<String, UsageStat>,
+}
+
+impl UsageStats {
+    pub fn record(&mut self, app_id: &str) {
+        let now = Utc::now();
+        let stat = self.by_id.entry(app_id.to_string()).or_default();
+        stat.use_count += 1;
+        stat.last_used = Some(now);
+        if stat.recent_visits.len() >= MAX_RECENT_VISITS {
+            stat.recent_visits.remove(0);
+        }
+        stat.recent_visits.push(now);
+    }
+
+    pub fn get(&self, app_id: &str) -> &UsageStat {
+        self.by_id.get(app_id).unwrap_or(UsageStat::unused())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_id.is_empty()
+    }
+}
+
+// 旧版本的条目格式 (apps_cache_v2.bin)，使用次数保存在条目中，启动时迁移到使用统计。
+// bincode 按字段顺序读取，字段与当时的 SearchResult 一一对应 (score 不序列化)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LegacySearchResult {
+    pub id: String,
+    pub title: String,
+    pub subtitle: String,
+    pub title_pinyin: String,
+    pub title_acronym: String,
+    pub action_type: String,
+    pub action_data: String,
+    pub use_count: u32,
+    pub last_used: Option<DateTime<Utc>>,
+    pub file_type: String,
+}
+
+impl From<Vec<LegacySearchResult>> for UsageStats {
+    fn from(legacy: Vec<LegacySearchResult>) -> Self {
+        let by_id = legacy.into_iter()
+            .filter(|app| app.use_count > 0)
+            .map(|app| (app.id, UsageStat {
+                use_count: app.use_count,
+                last_used: app.last_used,
+                recent_visits: app.last_used.into_iter().collect(),
+            }))
+            .collect();
+        Self { by_id }
     }
 }
 
 // --- 用户习惯记录 ---
+// 记录 "输入某个搜索词后选择了哪个条目"。搜索词按字符存成前缀树，
+// 输入 "chr" 选过 Chrome 后，输入 "ch"、"chro" 时 Chrome 同样加分：
+// 完全相同的搜索词权重最大，长度每相差一个字符权重减半。
+// 为了不让几次误点永久压住正确的结果，也不让记录文件无限增长：
+// - 次数有上限，且自上次选择起按半衰期衰减，衰减到很小时删除
+// - 同一搜索词下选择了别的条目时，其他条目的次数减半
+// - 每个搜索词最多记录几个条目，总记录数有上限 (超出时删除最弱的，留出余量避免每次记录都重建)
+// - 条目从索引中消失后删除指向它的记录
+const HABIT_WEIGHT: f64 = 1000.0;
+const HABIT_MAX_DISTANCE: usize = 6;       // 相差更多字符的搜索词不再参考
+const HABIT_MAX_COUNT: f64 = 5.0;          // 单个搜索词下单个条目的次数上限
+const HABIT_HALF_LIFE_DAYS: f64 = 30.0;    // 多久没有再选择时次数减半
+const HABIT_MIN_COUNT: f64 = 0.1;          // 衰减到此以下的记录删除
+const HABIT_MAX_APPS_PER_QUERY: usize = 8;
+const HABIT_MAX_ENTRIES: usize = 5000;
+const HABIT_PRUNED_ENTRIES: usize = 4000;  // 超出上限时删减到的数量
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct UserHabits {
+    nodes: Vec<HabitNode>, // nodes[0] 为根节点 (空搜索词)，第一次记录时创建
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+struct HabitNode {
+    children: HashMap<char, u32>,
+    apps: HashMap<String, HabitEntry>, // 以该节点结尾的搜索词: { AppID: 选择记录 }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct HabitEntry {
+    count: f64,                // last_used 时的次数
+    last_used: DateTime<Utc>,  // 上次选择的时间
+}
+
+impl HabitEntry {
+    // 衰减后的次数
+    fn current(&self, now: DateTime<Utc>) -> f64 {
+        let age_days = (now - self.last_used).num_seconds().max(0) as f64 / 86400.0;
+        self.count * 0.5f64.powf(age_days / HABIT_HALF_LIFE_DAYS)
+    }
+}
+
+// 旧版本的习惯记录格式 (user_habits.bin)，启动时迁移到前缀树
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct LegacyUserHabits {
     // Key: 搜索词, Value: { AppID: 点击次数 }
     pub history: HashMap<String, HashMap<String, u32>>,
 }
 
+impl From<LegacyUserHabits> for UserHabits {
+    fn from(legacy: LegacyUserHabits) -> Self {
+        // 旧记录没有时间，从迁移时开始衰减
+        let now = Utc::now();
+        let mut habits = Self::default();
+        for (query, apps) in legacy.history {
+            for (app_id, count) in apps {
+                let entry = HabitEntry { count: (count as f64).min(HABIT_MAX_COUNT), last_used: now };
+                habits.node_mut(&query).apps.insert(app_id, entry);
+            }
+        }
+        habits.prune(|_| true);
+        habits
+    }
+}
+
 impl UserHabits {
+    pub fn is_empty(&self) -> bool {
+        self.nodes.iter().all(|n| n.apps.is_empty())
+    }
+
     pub fn record(&mut self, query: &str, app_id: &str) {
         let query = query.trim().to_lowercase();
         if query.is_empty() { return; }
-        let entry = self.history.entry(query).or_insert_with(HashMap::new);
-        let count = entry.entry(app_id.to_string()).or_insert(0);
-        *count += 1;
+        let now = Utc::now();
+        let apps = &mut self.node_mut(&query).apps;
+
+        // 选择了别的条目：之前的选择可能是误点，减半
+        for (id, entry) in apps.iter_mut() {
+            if id != app_id {
+                entry.count *= 0.5;
+            }
+        }
+        apps.retain(|id, entry| id == app_id || entry.current(now) >= HABIT_MIN_COUNT);
+        let count = apps.get(app_id).map_or(0.0, |e| e.current(now));
+        apps.insert(app_id.to_string(), HabitEntry { count: (count + 1.0).min(HABIT_MAX_COUNT), last_used: now });
+
+        if apps.len() > HABIT_MAX_APPS_PER_QUERY {
+            let weakest = apps.iter()
+                .filter(|(id, _)| id.as_str() != app_id)
+                .min_by(|a, b| a.1.current(now).total_cmp(&b.1.current(now)))
+                .map(|(id, _)| id.clone());
+            if let Some(id) = weakest {
+                apps.remove(&id);
+            }
+        }
+
+        if self.nodes.iter().map(|n| n.apps.len()).sum::<usize>() > HABIT_MAX_ENTRIES {
+            self.prune(|_| true);
+        }
     }
 
-    pub fn get_weight(&self, query: &str, app_id: &str) -> i64 {
-        let query = query.trim().to_lowercase();
-        if let Some(apps) = self.history.get(&query) {
-            if let Some(&count) = apps.get(app_id) {
-                // 历史记录权重极大(1000分)，保证用过的就在最上面
-                return count as i64 * 1000; 
+    // 删除衰减殆尽的记录以及 keep 返回 false 的条目 (已不在索引中) 的记录，
+    // 总数超出上限时只保留最强的一部分。返回是否删除了记录
+    pub fn prune(&mut self, keep: impl Fn(&str) -> bool) -> bool {
+        let now = Utc::now();
+        let mut entries = Vec::new();
+        let mut removed = false;
+        self.collect(0, &mut String::new(), &mut entries);
+        entries.retain(|(_, id, entry)| {
+            let keep = keep(id) && entry.current(now) >= HABIT_MIN_COUNT;
+            removed |= !keep;
+            keep
+        });
+        if entries.len() > HABIT_MAX_ENTRIES {
+            entries.sort_by(|a, b| b.2.current(now).total_cmp(&a.2.current(now)));
+            entries.truncate(HABIT_PRUNED_ENTRIES);
+            removed = true;
+        }
+        if !removed {
+            return false;
+        }
+
+        // 重建前缀树，去掉不再有记录的节点
+        self.nodes.clear();
+        for (query, id, entry) in entries {
+            self.node_mut(&query).apps.insert(id, entry);
+        }
+        true
+    }
+
+    // 深度优先收集 (搜索词, AppID, 记录)
+    fn collect(&self, node: usize, query: &mut String, out: &mut Vec<(String, String, HabitEntry)>) {
+        let Some(n) = self.nodes.get(node) else { return };
+        for (id, entry) in &n.apps {
+            out.push((query.clone(), id.clone(), *entry));
+        }
+        for (&c, &child) in &n.children {
+            query.push(c);
+            self.collect(child as usize, query, out);
+            query.pop();
+        }
+    }
+
+    // 搜索词对应的节点，不存在时创建
+    fn node_mut(&mut self, query: &str) -> &mut HabitNode {
+        if self.nodes.is_empty() {
+            self.nodes.push(HabitNode::default());
+        }
+        let mut node = 0;
+        for c in query.chars() {
+            node = match self.nodes[node].children.get(&c) {
+                Some(&child) => child as usize,
+                None => {
+                    let child = self.nodes.len();
+                    self.nodes.push(HabitNode::default());
+                    self.nodes[node].children.insert(c, child as u32);
+                    child
+                }
+            };
+        }
+        &mut self.nodes[node]
+    }
+
+    // 该搜索词下各条目的习惯权重 (AppID -> 分数)。每次搜索算一次，
+    // 只需走一遍查询路径 (更短的搜索词) 和其下几层子树 (更长的搜索词)
+    pub fn weights(&self, query: &str, now: DateTime<Utc>) -> HashMap<&str, i64> {
+        let mut weights = HashMap::new();
+        let query: Vec<char> = query.trim().to_lowercase().chars().collect();
+        if query.is_empty() || self.nodes.is_empty() {
+            return weights;
+        }
+
+        // 查询路径上的节点：之前输入过的、查询的前缀 (以及查询本身)
+        let mut node = 0;
+        for (depth, c) in query.iter().enumerate() {
+            match self.nodes[node].children.get(c) {
+                Some(&child) => node = child as usize,
+                None => return weights,
+            }
+            let distance = query.len() - depth - 1;
+            if distance <= HABIT_MAX_DISTANCE {
+                self.add_weights(node, distance, now, &mut weights);
             }
         }
-        0
+
+        // 查询节点的子树：以查询开头的更长搜索词
+        let mut stack: Vec<(usize, usize)> = self.nodes[node].children.values().map(|&c| (c as usize, 1)).collect();
+        while let Some((node, distance)) = stack.pop() {
+            self.add_weights(node, distance, now, &mut weights);
+            if distance < HABIT_MAX_DISTANCE {
+                stack.extend(self.nodes[node].children.values().map(|&c| (c as usize, distance + 1)));
+            }
+        }
+        weights
+    }
+
+    fn add_weights<'a>(&'a self, node: usize, distance: usize, now: DateTime<Utc>, weights: &mut HashMap<&'a str, i64>) {
+        // 历史记录权重极大(完全相同的搜索词每次 1000 分)，保证用过的就在最上面
+        let scale = HABIT_WEIGHT * 0.5f64.powi(distance as i32);
+        for (app_id, entry) in &self.nodes[node].apps {
+            *weights.entry(app_id.as_str()).or_insert(0) += (entry.current(now) * scale).round() as i64;
+        }
+    }
+}
+
+// --- 扫描规则 ---
+// 每条规则描述一个扫描根目录及其过滤方式，默认规则见 scanner::default_scan_rules
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ScanRule {
+    // 目录路径 (支持 ~ 与环境变量)，或以 @ 开头的内置来源:
+    // @start_menu @desktop @documents @downloads @registry @drives @applications @path
+    pub path: String,
+    pub max_depth: usize,
+    pub include: Vec<String>,    // 文件需匹配其中之一的 glob (匹配完整路径)，空表示不限制
+    pub exclude: Vec<String>,    // 跳过的文件/目录 glob，如 "/mnt/backup/**"
+    pub extensions: Vec<String>, // 允许的扩展名 (不带点)，空表示使用平台默认值
+    pub commands: bool,          // 收录根目录第一层无扩展名的可执行文件 (Unix 命令)
+    pub follow_symlinks: bool,
+    pub ignore_files: bool,      // 遵循各目录中的 .gitignore / .ignore / .omniboxignore
+}
+
+impl ScanRule {
+    pub fn new(path: &str, max_depth: usize) -> Self {
+        Self {
+            path: path.to_string(),
+            max_depth,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            extensions: Vec::new(),
+            commands: false,
+            follow_symlinks: true,
+            ignore_files: true,
+        }
     }
 }
 
 // --- 应用设置 ---
+// 保存为 JSON (settings.json)：新增字段后旧文件仍能读取，缺少的字段取默认值
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct AppSettings {
     pub max_results: usize,
     pub enable_autostart: bool,
+    // 运行命令行工具的终端，"{}" 为命令占位符，如 "kitty --hold {}"；留空则自动检测
+    pub terminal: String,
+    pub scan_rules: Vec<ScanRule>,
+    pub folder_blacklist: Vec<String>, // 任何规则下都不进入的目录名 (忽略大小写)
+    pub noise_keywords: Vec<String>,   // 文件名包含这些词的程序不收录 (卸载程序、安装包等)
+    pub frecency_half_life_days: f64,  // 使用记录的半衰期 (天)，0 表示不衰减、只看总次数
+    pub shuangpin: String,             // 双拼方案: "xiaohe" "microsoft" "ziranma"，留空表示不使用双拼
+    pub fuzzy_pinyin: Vec<(String, String)>, // 模糊音等价对，如 ("z", "zh")、("n", "l")、("an", "ang")
+    pub transliteration: Vec<String>,  // 生成读法的语言: "zh" 拼音、"ja" 假名罗马字、"ko" 韩文罗马字与初声
+}
+
+impl AppSettings {
+    // 影响扫描结果的设置是否不同 (修改后需要重新扫描)
+    pub fn scan_config_differs(&self, other: &AppSettings) -> bool {
+        self.scan_rules != other.scan_rules
+            || self.folder_blacklist != other.folder_blacklist
+            || self.noise_keywords != other.noise_keywords
+            || self.transliteration != other.transliteration
+    }
 }
 
 impl Default for AppSettings {
@@ -82,6 +600,163 @@ impl Default for AppSettings {
         Self {
             max_results: 100,
             enable_autostart: false,
+            terminal: String::new(),
+            scan_rules: crate::scanner::default_scan_rules(),
+            folder_blacklist: crate::scanner::DEFAULT_FOLDER_BLACKLIST.iter().map(|s| s.to_string()).collect(),
+            noise_keywords: crate::scanner::DEFAULT_NOISE_KEYWORDS.iter().map(|s| s.to_string()).collect(),
+            frecency_half_life_days: 14.0,
+            shuangpin: String::new(),
+            fuzzy_pinyin: Vec::new(),
+            transliteration: vec!["zh".into(), "ja".into(), "ko".into()],
         }
     }
-}
\ No newline at end of file
+}
+
+// 旧版本的设置格式 (bincode 的 settings.bin)，启动时迁移到 settings.json。
+// 之后加入的字段都在这两个之后，按这个前缀读取
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LegacyAppSettings {
+    pub max_results: usize,
+    pub enable_autostart: bool,
+}
+
+impl Default for LegacyAppSettings {
+    fn default() -> Self {
+        let settings = AppSettings::default();
+        Self { max_results: settings.max_results, enable_autostart: settings.enable_autostart }
+    }
+}
+
+impl From<LegacyAppSettings> for AppSettings {
+    fn from(legacy: LegacyAppSettings) -> Self {
+        Self { max_results: legacy.max_results, enable_autostart: legacy.enable_autostart, ..Self::default() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_json_fills_missing_fields_with_defaults() {
+        let settings: AppSettings = serde_json::from_str(r#"{"max_results": 20, "enable_autostart": true}"#).unwrap();
+        assert_eq!(settings.max_results, 20);
+        assert!(settings.enable_autostart);
+        assert_eq!(settings.transliteration, AppSettings::default().transliteration);
+        assert_eq!(settings.frecency_half_life_days, 14.0);
+    }
+
+    #[test]
+    fn legacy_settings_keep_the_original_fields() {
+        // 之后各版本的 settings.bin 都以这两个字段开头
+        let old = AppSettings { max_results: 30, enable_autostart: true, ..AppSettings::default() };
+        let legacy: LegacyAppSettings = bincode::deserialize(&bincode::serialize(&old).unwrap()).unwrap();
+        let migrated = AppSettings::from(legacy);
+        assert_eq!(migrated.max_results, 30);
+        assert!(migrated.enable_autostart);
+    }
+
+    #[test]
+    fn recent_use_outranks_a_large_old_count() {
+        let now = "2025-06-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let days_ago = |days: i64| now - chrono::Duration::days(days);
+        let last_year = UsageStat { use_count: 500, last_used: Some(days_ago(365)), recent_visits: vec![days_ago(366), days_ago(365)] };
+        let this_week = UsageStat { use_count: 10, last_used: Some(days_ago(1)), recent_visits: (1..=5).rev().map(days_ago).collect() };
+        assert!(this_week.frecency(now, 14.0) > last_year.frecency(now, 14.0));
+
+        // 每经过一个半衰期减半；半衰期为 0 时只看总次数
+        let once = UsageStat { use_count: 4, last_used: Some(days_ago(14)), recent_visits: vec![days_ago(14)] };
+        assert!((once.frecency(now, 14.0) - 2.0).abs() < 1e-9);
+        assert_eq!(last_year.frecency(now, 0.0), 500.0);
+        assert_eq!(UsageStat::unused().frecency(now, 14.0), 0.0);
+
+        // 只保留最近几次的时间
+        let mut usage = UsageStats::default();
+        for _ in 0..MAX_RECENT_VISITS + 2 {
+            usage.record("a");
+        }
+        assert_eq!(usage.get("a").use_count as usize, MAX_RECENT_VISITS + 2);
+        assert_eq!(usage.get("a").recent_visits.len(), MAX_RECENT_VISITS);
+    }
+
+    #[test]
+    fn habits_weight_prefixes_by_distance() {
+        let now = Utc::now();
+        let mut habits = UserHabits::default();
+        assert!(habits.is_empty() && habits.weights("ch", now).is_empty());
+        habits.record(" Chr ", "chrome");
+        habits.record("chromium", "chromium");
+
+        let weights = habits.weights("chr", now);
+        assert_eq!(weights["chrome"], 1000);
+        // 长 5 个字符的搜索词
+        assert_eq!(weights["chromium"], 31);
+        assert_eq!(habits.weights("ch", now)["chrome"], 500);
+        assert_eq!(habits.weights("CHRO", now)["chrome"], 500);
+        assert_eq!(habits.weights("chrom", now)["chrome"], 250);
+        assert!(!habits.weights("x", now).contains_key("chrome"));
+        assert!(!habits.weights("chx", now).contains_key("chrome"));
+    }
+
+    #[test]
+    fn legacy_habits_are_migrated_with_capped_counts() {
+        let now = Utc::now();
+        let mut legacy = LegacyUserHabits::default();
+        legacy.history.insert("wx".into(), HashMap::from([("wechat".to_string(), 3)]));
+        legacy.history.insert("c".into(), HashMap::from([("calc".to_string(), 40)]));
+        let habits = UserHabits::from(legacy);
+        assert_eq!(habits.weights("wx", now)["wechat"], 3000);
+        assert_eq!(habits.weights("w", now)["wechat"], 1500);
+        assert_eq!(habits.weights("c", now)["calc"], 5000);
+    }
+
+    #[test]
+    fn habits_recover_from_misclicks_and_decay() {
+        let now = Utc::now();
+        let mut habits = UserHabits::default();
+        // 误点 3 次后选了 2 次正确的条目，正确的条目排在前面
+        for _ in 0..3 {
+            habits.record("term", "wrong");
+        }
+        for _ in 0..2 {
+            habits.record("term", "right");
+        }
+        let weights = habits.weights("term", now);
+        assert!(weights["right"] > weights["wrong"], "{:?}", weights);
+
+        // 次数有上限，误点的记录被不断减半直到删除
+        for _ in 0..20 {
+            habits.record("term", "right");
+        }
+        assert_eq!(habits.weights("term", now)["right"], 5000);
+        assert!(!habits.weights("term", now).contains_key("wrong"));
+        // 一个半衰期后减半
+        assert_eq!(habits.weights("term", now + chrono::Duration::days(30))["right"], 2500);
+    }
+
+    #[test]
+    fn habits_are_capped_and_pruned() {
+        let now = Utc::now();
+        let mut habits = UserHabits::default();
+        for i in 0..20 {
+            habits.record("x", &format!("app{}", i));
+        }
+        let weights = habits.weights("x", now);
+        assert!(weights.len() <= HABIT_MAX_APPS_PER_QUERY && weights.contains_key("app19"));
+
+        habits.record("term", "gone");
+        assert!(habits.prune(|id| id != "gone"));
+        assert!(!habits.weights("term", now).contains_key("gone"));
+        assert!(!habits.prune(|_| true));
+
+        // 总数超出上限时删减到 HABIT_PRUNED_ENTRIES 左右，最近的记录保留
+        let mut habits = UserHabits::default();
+        let total = HABIT_MAX_ENTRIES + 1000;
+        for i in 0..total {
+            habits.record(&format!("q{}_", i), "a");
+        }
+        let kept = (0..total).filter(|i| habits.weights(&format!("q{}_", i), now).contains_key("a")).count();
+        assert!((HABIT_PRUNED_ENTRIES..=HABIT_MAX_ENTRIES).contains(&kept), "{}", kept);
+        assert!(habits.weights(&format!("q{}_", total - 1), now).contains_key("a"));
+    }
+}
@@ -0,0 +1,136 @@
+use unicode_normalization::char::{canonical_combining_class, compose};
+use unicode_normalization::UnicodeNormalization;
+
+// ==========================================
+// 文本归一化
+// ==========================================
+// 名称与查询在比较前都经过同样的处理，输入普通 ASCII 也能找到带重音符号或全角字符的名称：
+// 1. 兼容分解 (NFKC)：全角 "ＶＳ　Ｃｏｄｅ" -> "VS Code"，连字 "ﬁ" -> "fi"，半角片假名 -> 全角
+// 2. 大小写折叠
+// 3. 去掉拉丁字母的附加符号："Café" -> "cafe"、"Ångström" -> "angstrom"；
+//    不能分解的字母按惯例展开："ß" -> "ss"、"æ" -> "ae"、"ø" -> "o"
+// 逐字符处理，归一化结果的每个字符都能对应回原文的字符 (高亮用)。显示时仍使用原文
+
+pub fn fold(text: &str) -> String {
+    if text.is_ascii() {
+        return text.to_ascii_lowercase();
+    }
+    fold_chars(text).into_iter().map(|(c, _)| c).collect()
+}
+
+// 归一化结果，以及其中每个字符对应的原文字符下标
+pub fn fold_indexed(text: &str) -> (String, Vec<usize>) {
+    fold_chars(text).into_iter().unzip()
+}
+
+// 归一化后的每个字符及其对应的原文字符下标。
+// 浊点等组合用符号与前一个字符组合 (半角 "ｶﾞ" 两个字符 -> "ガ")，与整体做 NFKC 的结果一致；组合后的字符对应基本字符
+pub fn fold_chars(text: &str) -> Vec<(char, usize)> {
+    let mut out: Vec<(char, usize)> = Vec::with_capacity(text.len());
+    let mut buf = String::new();
+    for (i, c) in text.chars().enumerate() {
+        buf.clear();
+        fold_char(c, &mut buf);
+        for c in buf.chars() {
+            if canonical_combining_class(c) != 0 {
+                if let Some(last) = out.last_mut() {
+                    if let Some(composed) = compose(last.0, c) {
+                        last.0 = composed;
+                        continue;
+                    }
+                }
+            }
+            out.push((c, i));
+        }
+    }
+    out
+}
+
+// 单个字符的归一化结果追加到 out；可能为空 (单独的附加符号) 或多个字符 ("ß" -> "ss")
+fn fold_char(c: char, out: &mut String) {
+    if c.is_ascii() {
+        out.push(c.to_ascii_lowercase());
+        return;
+    }
+    // 韩文兼容字母保持原样：兼容分解会把 "ㅋ" 变为组合用的初声字母 (U+110F)，与初声读法 "ㅋㅋㅇㅌ" 对不上
+    if is_compatibility_jamo(c) {
+        out.push(c);
+        return;
+    }
+    // 先分解再组合：去掉附加符号后，韩文音节、带浊点的假名等仍恢复为一个字符
+    for c in std::iter::once(c).nfkd().filter(|&c| !is_diacritic(c)).nfc() {
+        for c in c.to_lowercase() {
+            match expand(c) {
+                Some(s) => out.push_str(s),
+                None => out.push(c),
+            }
+        }
+    }
+}
+
+// 拉丁、希腊、西里尔字母上的组合附加符号 (重音、变音、软音符等)；假名的浊点不在此列
+fn is_diacritic(c: char) -> bool {
+    matches!(c, '\u{0300}'..='\u{036F}' | '\u{1AB0}'..='\u{1AFF}' | '\u{1DC0}'..='\u{1DFF}' | '\u{20D0}'..='\u{20FF}')
+}
+
+fn is_compatibility_jamo(c: char) -> bool {
+    matches!(c, '\u{3131}'..='\u{318E}')
+}
+
+// 不能分解为 "基本字母 + 附加符号" 的字母
+fn expand(c: char) -> Option<&'static str> {
+    Some(match c {
+        'ß' => "ss",
+        'æ' => "ae",
+        'œ' => "oe",
+        'ø' => "o",
+        'đ' | 'ð' => "d",
+        'ł' => "l",
+        'ħ' => "h",
+        'ı' => "i",
+        'þ' => "th",
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn width_case_and_accents() {
+        assert_eq!(fold("ＶＳ　Ｃｏｄｅ"), "vs code");
+        assert_eq!(fold("Café"), "cafe");
+        assert_eq!(fold("Ångström"), "angstrom");
+        assert_eq!(fold("ﬁle"), "file");
+        assert_eq!(fold("Straße"), "strasse");
+        assert_eq!(fold("Ærø Łódź"), "aero lodz");
+        assert_eq!(fold("ΆΛΦΑ"), "αλφα");
+        assert_eq!(fold("plain ASCII"), "plain ascii");
+    }
+
+    #[test]
+    fn kana_and_hangul_stay_composed() {
+        assert_eq!(fold("ｶﾞｲﾄﾞ"), "ガイド");
+        assert_eq!(fold("カ\u{3099}"), "ガ");
+        assert_eq!(fold("ﾊﾟｽ"), "パス");
+        assert_eq!(fold("카카오톡"), "카카오톡");
+        assert_eq!(fold("ㅋㅋㅇㅌ"), "ㅋㅋㅇㅌ");
+        assert_eq!(fold("微信"), "微信");
+    }
+
+    #[test]
+    fn folded_chars_map_back_to_the_original() {
+        let (folded, origins) = fold_indexed("Straße");
+        assert_eq!(folded, "strasse");
+        assert_eq!(origins, vec![0, 1, 2, 3, 4, 4, 5]);
+        // 浊点并入前一个字符，单独的附加符号没有对应的字符
+        let (folded, origins) = fold_indexed("ｶﾞｲ");
+        assert_eq!(folded, "ガイ");
+        assert_eq!(origins, vec![0, 2]);
+        let (folded, origins) = fold_indexed("e\u{301}x");
+        assert_eq!(folded, "ex");
+        assert_eq!(origins, vec![0, 2]);
+        assert_eq!(fold_chars("ａ"), vec![('a', 0)]);
+    }
+}
@@ -0,0 +1,285 @@
+use crate::models::AppSettings;
+use once_cell::sync::Lazy;
+use pinyin::ToPinyinMulti;
+use std::collections::HashSet;
+
+// ==========================================
+// 拼音输入方式
+// ==========================================
+// 标题的拼音按全拼与首字母保存。为了让习惯双拼、或分不清某些读音的用户也能搜到，
+// 查询中的每个词除原样外还会生成其他拼法，任意一种拼法匹配上即可 (得分略低于原样匹配)：
+// - 双拼：按设置中的方案每两个键解码为一个音节，"wwxb" (小鹤) -> "weixin"
+// - 模糊音：设置中的等价对 (如 z=zh、n=l、an=ang) 互相替换，"zongwen" -> "zhongwen"
+
+// 每个词最多的拼法数 (含原样)
+const MAX_SPELLINGS: usize = 16;
+
+// 所有合法的音节 (ü 写作 v)，用于排除双拼解码出的无效组合
+static SYLLABLES: Lazy<HashSet<String>> = Lazy::new(|| {
+    let mut syllables = HashSet::new();
+    for c in ('\u{3400}'..='\u{9fff}').filter_map(|c| c.to_pinyin_multi()) {
+        for p in c {
+            syllables.insert(p.plain().replace('ü', "v"));
+        }
+    }
+    syllables
+});
+
+struct Shuangpin {
+    name: &'static str,
+    // 韵母键 -> 韵母
+    finals: &'static [(char, &'static [&'static str])],
+    // 零声母音节的引导键：微软为 o + 韵母键；None 时 (小鹤、自然码) 为韵母首字母 + 第二个字母或韵母键
+    zero_key: Option<char>,
+}
+
+// zh / ch / sh 在三种方案中都是 v / i / u
+const SCHEMES: &[Shuangpin] = &[
+    Shuangpin {
+        name: "xiaohe",
+        finals: &[
+            ('q', &["iu"]), ('w', &["ei"]), ('e', &["e"]), ('r', &["uan", "van"]), ('t', &["ue", "ve"]),
+            ('y', &["un", "vn"]), ('u', &["u"]), ('i', &["i"]), ('o', &["uo", "o"]), ('p', &["ie"]),
+            ('a', &["a"]), ('s', &["ong", "iong"]), ('d', &["ai"]), ('f', &["en"]), ('g', &["eng"]),
+            ('h', &["ang"]), ('j', &["an"]), ('k', &["ing", "uai"]), ('l', &["iang", "uang"]),
+            ('z', &["ou"]), ('x', &["ia", "ua"]), ('c', &["ao"]), ('v', &["ui", "v"]), ('b', &["in"]),
+            ('n', &["iao"]), ('m', &["ian"]),
+        ],
+        zero_key: None,
+    },
+    Shuangpin {
+        name: "microsoft",
+        finals: &[
+            ('q', &["iu"]), ('w', &["ia", "ua"]), ('e', &["e"]), ('r', &["uan", "er"]), ('t', &["ue"]),
+            ('y', &["uai", "v"]), ('u', &["u"]), ('i', &["i"]), ('o', &["uo", "o"]), ('p', &["un"]),
+            ('a', &["a"]), ('s', &["ong", "iong"]), ('d', &["iang", "uang"]), ('f', &["en"]), ('g', &["eng"]),
+            ('h', &["ang"]), ('j', &["an"]), ('k', &["ao"]), ('l', &["ai"]), (';', &["ing"]),
+            ('z', &["ei"]), ('x', &["ie"]), ('c', &["iao"]), ('v', &["ui", "ve"]), ('b', &["ou"]),
+            ('n', &["in"]), ('m', &["ian"]),
+        ],
+        zero_key: Some('o'),
+    },
+    Shuangpin {
+        name: "ziranma",
+        finals: &[
+            ('q', &["iu"]), ('w', &["ia", "ua"]), ('e', &["e"]), ('r', &["uan", "van"]), ('t', &["ue", "ve"]),
+            ('y', &["uai", "ing"]), ('u', &["u"]), ('i', &["i"]), ('o', &["uo", "o"]), ('p', &["un", "vn"]),
+            ('a', &["a"]), ('s', &["ong", "iong"]), ('d', &["iang", "uang"]), ('f', &["en"]), ('g', &["eng"]),
+            ('h', &["ang"]), ('j', &["an"]), ('k', &["ao"]), ('l', &["ai"]), ('z', &["ei"]), ('x', &["ie"]),
+            ('c', &["iao"]), ('v', &["ui", "v"]), ('b', &["ou"]), ('n', &["in"]), ('m', &["ian"]),
+        ],
+        zero_key: None,
+    },
+];
+
+pub struct PinyinInput<'a> {
+    shuangpin: Option<&'static Shuangpin>,
+    fuzzy: &'a [(String, String)],
+}
+
+impl<'a> PinyinInput<'a> {
+    pub fn new(settings: &'a AppSettings) -> Self {
+        Self {
+            shuangpin: SCHEMES.iter().find(|s| s.name == settings.shuangpin),
+            fuzzy: &settings.fuzzy_pinyin,
+        }
+    }
+
+    // token 的所有拼法 (已小写)：原样在前，然后是它的模糊音变体、双拼解码结果及其模糊音变体
+    pub fn spellings(&self, token: &str) -> Vec<String> {
+        let mut spellings = vec![token.to_string()];
+        if !token.chars().all(|c| c.is_ascii_lowercase() || c == ';') {
+            return spellings;
+        }
+        self.add_fuzzy(token, &mut spellings);
+        if let Some(scheme) = self.shuangpin {
+            for decoded in scheme.decode(token) {
+                if !spellings.contains(&decoded) && spellings.len() < MAX_SPELLINGS {
+                    spellings.push(decoded.clone());
+                }
+                self.add_fuzzy(&decoded, &mut spellings);
+            }
+        }
+        spellings
+    }
+
+    // 依次替换 1 处、2 处...模糊音，结果追加到 out
+    fn add_fuzzy(&self, spelling: &str, out: &mut Vec<String>) {
+        if self.fuzzy.is_empty() {
+            return;
+        }
+        let mut queue = vec![spelling.to_string()];
+        let mut i = 0;
+        while i < queue.len() {
+            for (a, b) in self.fuzzy {
+                for (from, to) in [(a.as_str(), b.as_str()), (b.as_str(), a.as_str())] {
+                    for variant in substitutions(&queue[i], from, to) {
+                        if out.len() >= MAX_SPELLINGS {
+                            return;
+                        }
+                        if !queue.contains(&variant) {
+                            if !out.contains(&variant) {
+                                out.push(variant.clone());
+                            }
+                            queue.push(variant);
+                        }
+                    }
+                }
+            }
+            i += 1;
+        }
+    }
+}
+
+// 把 text 中的某一处 from 换成 to 的所有结果
+fn substitutions(text: &str, from: &str, to: &str) -> Vec<String> {
+    if from.is_empty() {
+        return Vec::new();
+    }
+    // 声母 (如 z / zh、n / l) 后面必须是韵母，"an" 中的 n 不是声母
+    let initial = !from.chars().chain(to.chars()).any(is_vowel);
+    // from 是 to 的前缀 (z -> zh、an -> ang) 时，已经是 to 的位置不再替换
+    let suffix = to.strip_prefix(from);
+
+    text.match_indices(from)
+        .filter(|&(i, _)| {
+            let rest = &text[i + from.len()..];
+            if suffix.is_some_and(|s| rest.starts_with(s)) {
+                return false;
+            }
+            !initial || rest.chars().next().is_some_and(is_vowel)
+        })
+        .map(|(i, _)| format!("{}{}{}", &text[..i], to, &text[i + from.len()..]))
+        .collect()
+}
+
+fn is_vowel(c: char) -> bool {
+    matches!(c, 'a' | 'e' | 'i' | 'o' | 'u' | 'v')
+}
+
+// 双拼的声母键
+fn initial(key: char) -> Option<&'static str> {
+    Some(match key {
+        'v' => "zh", 'i' => "ch", 'u' => "sh",
+        'b' => "b", 'p' => "p", 'm' => "m", 'f' => "f", 'd' => "d", 't' => "t", 'n' => "n", 'l' => "l",
+        'g' => "g", 'k' => "k", 'h' => "h", 'j' => "j", 'q' => "q", 'x' => "x", 'r' => "r",
+        'z' => "z", 'c' => "c", 's' => "s", 'y' => "y", 'w' => "w",
+        _ => return None,
+    })
+}
+
+impl Shuangpin {
+    // 每两个键解码为一个音节，最后单独的一个键视为声母 (正在输入中)。
+    // 一个韵母键可能对应多个韵母，结果可能有多个；任意两个键不构成音节时返回空
+    fn decode(&self, keys: &str) -> Vec<String> {
+        let keys: Vec<char> = keys.chars().collect();
+        let mut results = vec![String::new()];
+        for pair in keys.chunks(2) {
+            let syllables = match *pair {
+                [k1, k2] => self.syllables(k1, k2),
+                [k] => initial(k).map(String::from)
+                    .or_else(|| matches!(k, 'a' | 'e' | 'o').then(|| k.to_string()))
+                    .into_iter()
+                    .collect(),
+                _ => Vec::new(),
+            };
+            if syllables.is_empty() {
+                return Vec::new();
+            }
+            results = results.iter()
+                .flat_map(|r| syllables.iter().map(move |s| format!("{}{}", r, s)))
+                .take(MAX_SPELLINGS)
+                .collect();
+        }
+        results
+    }
+
+    fn syllables(&self, k1: char, k2: char) -> Vec<String> {
+        let finals = self.finals.iter().find(|(k, _)| *k == k2).map_or(&[][..], |(_, f)| *f);
+        let mut syllables = Vec::new();
+        match self.zero_key {
+            Some(zero) if k1 == zero => syllables.extend(finals.iter().map(|f| f.to_string())),
+            None if matches!(k1, 'a' | 'e' | 'o') => {
+                // 零声母：aa -> a、ai -> ai、ah -> ang
+                if k1 == k2 {
+                    syllables.push(k1.to_string());
+                }
+                syllables.push(format!("{}{}", k1, k2));
+                syllables.extend(finals.iter().filter(|f| f.starts_with(k1)).map(|f| f.to_string()));
+            }
+            _ => {
+                if let Some(initial) = initial(k1) {
+                    syllables.extend(finals.iter().map(|f| format!("{}{}", initial, f)));
+                }
+            }
+        }
+        syllables.retain(|s| SYLLABLES.contains(s));
+        syllables.dedup();
+        syllables
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(shuangpin: &str, fuzzy: &[(&str, &str)]) -> AppSettings {
+        AppSettings {
+            shuangpin: shuangpin.to_string(),
+            fuzzy_pinyin: fuzzy.iter().map(|(a, b)| (a.to_string(), b.to_string())).collect(),
+            ..AppSettings::default()
+        }
+    }
+
+    fn spellings(settings: &AppSettings, token: &str) -> Vec<String> {
+        PinyinInput::new(settings).spellings(token)
+    }
+
+    #[test]
+    fn original_spelling_comes_first() {
+        assert_eq!(spellings(&settings("", &[]), "wwxb"), vec!["wwxb"]);
+        assert_eq!(spellings(&settings("xiaohe", &[]), "wwxb")[0], "wwxb");
+        // 非拼音字符不生成其他拼法
+        assert_eq!(spellings(&settings("xiaohe", &[("z", "zh")]), "微信"), vec!["微信"]);
+    }
+
+    #[test]
+    fn shuangpin_schemes_decode_key_pairs() {
+        let xiaohe = settings("xiaohe", &[]);
+        assert!(spellings(&xiaohe, "wwxb").contains(&"weixin".to_string()));
+        assert!(spellings(&xiaohe, "vsuf").contains(&"zhongshen".to_string()));
+        assert!(spellings(&xiaohe, "ahjk").contains(&"angjing".to_string()));
+        // 最后单独的键按声母计 (正在输入)
+        assert!(spellings(&xiaohe, "wwx").contains(&"weix".to_string()));
+
+        let microsoft = settings("microsoft", &[]);
+        assert!(spellings(&microsoft, "wzxn").contains(&"weixin".to_string()));
+        assert!(spellings(&microsoft, "oj").contains(&"an".to_string()));
+        assert!(spellings(&microsoft, "x;").contains(&"xing".to_string()));
+
+        assert!(spellings(&settings("ziranma", &[]), "xy").contains(&"xing".to_string()));
+        // 解码出的不是合法音节时不生成
+        assert_eq!(spellings(&xiaohe, "fk"), vec!["fk"]);
+    }
+
+    #[test]
+    fn fuzzy_pairs_substitute_in_both_directions() {
+        let fuzzy = settings("", &[("z", "zh"), ("n", "l"), ("an", "ang"), ("en", "eng")]);
+        let zongwen = spellings(&fuzzy, "zongwen");
+        assert!(zongwen.contains(&"zhongwen".to_string()));
+        assert!(zongwen.contains(&"zhongweng".to_string()));
+        // 已经是 zh 的位置不再替换成 zhh
+        assert!(!spellings(&fuzzy, "zhang").iter().any(|s| s.contains("zhh")));
+        assert!(spellings(&fuzzy, "zhang").contains(&"zan".to_string()));
+        assert!(spellings(&fuzzy, "lv").contains(&"nv".to_string()));
+        // 声母替换要求后面是韵母："an" 中的 n 不是声母
+        assert!(!spellings(&fuzzy, "an").contains(&"al".to_string()));
+        assert!(spellings(&fuzzy, "zhangzhengzhan").len() <= MAX_SPELLINGS);
+    }
+
+    #[test]
+    fn decoded_shuangpin_gets_fuzzy_variants() {
+        let both = settings("xiaohe", &[("z", "zh")]);
+        assert!(spellings(&both, "vsww").contains(&"zongwei".to_string()));
+    }
+}
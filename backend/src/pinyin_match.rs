@@ -0,0 +1,197 @@
+use crate::models::PinyinReading;
+
+// ==========================================
+// 按音节边界的拼音匹配
+// ==========================================
+// 查询词与某种读法逐字对齐，每个字可以输入：
+// - 整个音节 ("weixin")
+// - 声母 / 首字母 ("wx"，zh / ch / sh 也可以输入两个字母)
+// - 二者混合 ("weix"、"wxin")
+// 最后一个字还可以只输入音节的一部分 ("weixi")。
+// 匹配必须从某个字的开头开始，"inx" 不会匹配 "weixin"，"xinw" 也不会。
+// 匹配的字之间允许跳过其他字 ("wxkf" -> "微信开发者工具")，但每断开一次都会在打分时扣分；
+// 空格、标点等分隔符可以直接跳过，不算断开 ("qqyinyue" -> "QQ 音乐")
+
+pub struct SyllableMatch {
+    // 多字母的音节都只输入了声母 ("wx"、"qqyl")，打分时按首字母匹配计
+    pub abbreviated: bool,
+    // 匹配的字之间断开的次数
+    pub gaps: u32,
+    // 匹配到的字符下标 (递增)
+    pub chars: Vec<usize>,
+}
+
+// 已匹配的字用到的输入方式：还没有多字母音节 / 只输入了声母 / 有输入超出声母的
+const NEUTRAL: usize = 0;
+const INITIAL: usize = 1;
+const FULL: usize = 2;
+const KINDS: usize = 3;
+
+// 在一种读法中匹配 token；有多种对齐方式时取断开次数最少的，其次是只输入声母的
+pub fn match_syllables(reading: &PinyinReading, token: &str) -> Option<SyllableMatch> {
+    let query = token.as_bytes();
+    let m = query.len();
+    if m == 0 {
+        return None;
+    }
+
+    // 状态：(已匹配的查询字节数, 上一个字是否匹配, 输入方式)，值为最少断开次数。逐字推进
+    let width = 2 * KINDS;
+    let states = (m + 1) * width;
+    let state = |p: usize, prev: bool, kind: usize| p * width + prev as usize * KINDS + kind;
+    let mut gaps = vec![u32::MAX; states];
+    gaps[state(0, false, NEUTRAL)] = 0;
+    // 每个字一层：到达该状态的上一层状态，以及是否匹配了这个字
+    let mut back: Vec<(usize, bool)> = Vec::new();
+    // (断开次数, 不是只输入声母, 最后一个字, 最后一个字之前的状态)
+    let mut best: Option<(u32, bool, usize, usize)> = None;
+
+    let mut offset = 0;
+    for (c, &len) in reading.syllables.iter().enumerate() {
+        let segment = reading.full.get(offset..offset + len as usize)?;
+        offset += len as usize;
+        let separator = !segment.chars().any(char::is_alphanumeric);
+
+        let layer = back.len();
+        back.resize(layer + states, (0, false));
+        let mut next = vec![u32::MAX; states];
+        let mut relax = |to: usize, g: u32, from: usize, matched: bool| {
+            if g < next[to] {
+                next[to] = g;
+                back[layer + to] = (from, matched);
+            }
+        };
+
+        for p in 0..m {
+            for prev in [false, true] {
+                for kind in 0..KINDS {
+                    let from = state(p, prev, kind);
+                    let g = gaps[from];
+                    if g == u32::MAX {
+                        continue;
+                    }
+                    // 跳过这个字：还没开始匹配或是分隔符时不算断开
+                    if p == 0 || separator {
+                        relax(from, g, from, false);
+                    } else {
+                        relax(state(p, false, kind), g + prev as u32, from, false);
+                    }
+                    // 匹配这个字
+                    for (consumed, used) in syllable_inputs(segment, &token[p..]) {
+                        let kind = kind.max(used);
+                        if p + consumed < m {
+                            relax(state(p + consumed, true, kind), g, from, true);
+                        } else if best.is_none_or(|b| (g, kind != INITIAL) < (b.0, b.1)) {
+                            best = Some((g, kind != INITIAL, c, from));
+                        }
+                    }
+                }
+            }
+        }
+        gaps = next;
+    }
+
+    // 从最后一个字往前还原匹配到的字符
+    let (g, not_abbreviated, last, mut s) = best?;
+    let mut chars = vec![last];
+    for c in (0..last).rev() {
+        let (from, matched) = back[c * states + s];
+        if matched {
+            chars.push(c);
+        }
+        s = from;
+    }
+    chars.reverse();
+    Some(SyllableMatch { abbreviated: !not_abbreviated, gaps: g, chars })
+}
+
+// 一个字在 rest 开头可以匹配的输入：(消耗的字节数, 输入方式)
+fn syllable_inputs(segment: &str, rest: &str) -> impl Iterator<Item = (usize, usize)> {
+    let mut inputs = [None; 4];
+    // 单个字母 / 字符的片段 (英文、数字、"啊") 整个输入，不算声母也不算全拼
+    let first = segment.chars().next().map_or(0, char::len_utf8);
+    if segment.len() <= first {
+        if first > 0 && rest.starts_with(segment) {
+            inputs[0] = Some((segment.len(), NEUTRAL));
+        }
+        return inputs.into_iter().flatten();
+    }
+
+    let initial = if ["zh", "ch", "sh"].iter().any(|s| segment.starts_with(s)) { 2 } else { first };
+    if rest.starts_with(segment) {
+        inputs[0] = Some((segment.len(), FULL));
+    }
+    for (i, len) in [first, initial].into_iter().enumerate() {
+        if (i == 0 || len != first) && rest.starts_with(&segment[..len]) {
+            inputs[1 + i] = Some((len, INITIAL));
+        }
+    }
+    // 最后一个字只输入了一部分
+    if rest.len() > initial && rest.len() < segment.len() && segment.starts_with(rest) {
+        inputs[3] = Some((rest.len(), FULL));
+    }
+    inputs.into_iter().flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 每个字的读音片段 -> 读法
+    fn reading(segments: &[&str]) -> PinyinReading {
+        PinyinReading {
+            full: segments.concat(),
+            acronym: segments.iter().map(|s| s.chars().next().map(String::from).unwrap_or_default()).collect(),
+            syllables: segments.iter().map(|s| s.len() as u8).collect(),
+        }
+    }
+
+    fn matched(segments: &[&str], token: &str) -> Option<(bool, u32, Vec<usize>)> {
+        match_syllables(&reading(segments), token).map(|m| (m.abbreviated, m.gaps, m.chars))
+    }
+
+    const WEIXIN: &[&str] = &["wei", "xin"];
+
+    #[test]
+    fn full_initial_and_mixed_input() {
+        assert_eq!(matched(WEIXIN, "weixin"), Some((false, 0, vec![0, 1])));
+        assert_eq!(matched(WEIXIN, "wx"), Some((true, 0, vec![0, 1])));
+        assert_eq!(matched(WEIXIN, "weix"), Some((false, 0, vec![0, 1])));
+        assert_eq!(matched(WEIXIN, "wxin"), Some((false, 0, vec![0, 1])));
+        // 最后一个字只输入一部分
+        assert_eq!(matched(WEIXIN, "weixi"), Some((false, 0, vec![0, 1])));
+        assert_eq!(matched(WEIXIN, "we"), Some((false, 0, vec![0])));
+        // zh / ch / sh 可以输入两个字母
+        assert_eq!(matched(&["zhong", "wen"], "zhw"), Some((true, 0, vec![0, 1])));
+    }
+
+    #[test]
+    fn matches_start_at_syllable_boundaries() {
+        assert_eq!(matched(WEIXIN, "inx"), None);
+        assert_eq!(matched(WEIXIN, "eix"), None);
+        assert_eq!(matched(WEIXIN, "xinw"), None);
+        assert_eq!(matched(WEIXIN, "x"), Some((true, 0, vec![1])));
+        assert_eq!(matched(WEIXIN, ""), None);
+    }
+
+    #[test]
+    fn gaps_are_counted_but_separators_are_free() {
+        let tool = ["wei", "xin", "kai", "fa", "zhe", "gong", "ju"];
+        assert_eq!(matched(&tool, "wxkf"), Some((true, 0, vec![0, 1, 2, 3])));
+        assert_eq!(matched(&tool, "wxgj"), Some((true, 1, vec![0, 1, 5, 6])));
+        assert_eq!(matched(&tool, "wgj"), Some((true, 1, vec![0, 5, 6])));
+        // "QQ 音乐"：空格是分隔符
+        let music = ["q", "q", " ", "yin", "yue"];
+        assert_eq!(matched(&music, "qqyinle"), None);
+        assert_eq!(matched(&music, "qqyinyue"), Some((false, 0, vec![0, 1, 3, 4])));
+        assert_eq!(matched(&music, "qqyy"), Some((true, 0, vec![0, 1, 3, 4])));
+    }
+
+    #[test]
+    fn prefers_fewest_gaps_then_abbreviation() {
+        // "a" 可以匹配第 0 个或第 2 个字，"ab" 连续匹配第 2、3 个字没有断开
+        assert_eq!(matched(&["an", "xx", "an", "bu"], "ab"), Some((true, 0, vec![2, 3])));
+        // 单字母片段 (英文) 不算只输入声母
+        assert_eq!(matched(&["v", "s"], "vs"), Some((false, 0, vec![0, 1])));
+    }
+}
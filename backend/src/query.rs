@@ -0,0 +1,364 @@
+use crate::models::{SearchResult, UsageStat};
+use crate::normalize;
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+// ==========================================
+// 查询语法
+// ==========================================
+// 在自由文本之外支持过滤条件，例如 `type:script deploy`、`ext:sh`、`in:~/work build`、
+// `used:>5`、`last:<7d`、`-uninstall`、`"visual studio"`：
+// - key:value 过滤条件 (key 不区分大小写)，值中可以用引号包含空格: in:"~/My Work"
+// - 带引号的短语必须原样 (不区分大小写与重音) 出现在标题、拼音、别名、关键词或文件名中
+// - 任意词、短语或过滤条件前加 "-" 表示排除
+// 未知的 key (如 "http://...") 按普通文本处理
+
+const FILTER_KEYS: &[&str] = &["type", "ext", "in", "used", "last"];
+
+// type: 可用的值 -> SearchResult.file_type
+const TYPE_NAMES: &[(&str, &str)] = &[
+    ("app", "Application"),
+    ("application", "Application"),
+    ("shortcut", "Shortcut"),
+    ("lnk", "Shortcut"),
+    ("script", "Script"),
+    ("command", "Command"),
+    ("cmd", "Command"),
+    ("tool", "System Tool"),
+    ("file", "File"),
+    ("folder", "Folder"),
+    ("dir", "Folder"),
+];
+
+// 查询有误时返回给前端显示；start / end 为出错部分在原查询中的字符位置
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct QueryError {
+    pub message: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Cmp {
+    Eq,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+}
+
+impl Cmp {
+    fn test<T: PartialOrd>(self, left: T, right: T) -> bool {
+        match self {
+            Cmp::Eq => left == right,
+            Cmp::Gt => left > right,
+            Cmp::Ge => left >= right,
+            Cmp::Lt => left < right,
+            Cmp::Le => left <= right,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Filter {
+    Text(String),         // 短语或被排除的词 (已归一化)
+    Type(&'static str),   // file_type
+    Ext(String),          // 扩展名 (小写，不带点)
+    In(PathBuf),          // 条目路径位于该目录下
+    Used(Cmp, u32),       // 使用次数
+    Last(Cmp, Duration),  // 距上次使用的时间 (从未使用视为无限久)
+}
+
+#[derive(Debug, Clone)]
+struct Term {
+    negated: bool,
+    filter: Filter,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ParsedQuery {
+    // 参与模糊匹配与打分的文本 (已归一化，见 normalize.rs)：普通词与短语，以空格连接
+    pub text: String,
+    terms: Vec<Term>,
+}
+
+impl ParsedQuery {
+    pub fn has_filters(&self) -> bool {
+        !self.terms.is_empty()
+    }
+
+    // 条目是否满足所有过滤条件
+    pub fn matches(&self, item: &SearchResult, usage: &UsageStat, now: DateTime<Utc>) -> bool {
+        self.terms.iter().all(|term| term.filter.matches(item, usage, now) != term.negated)
+    }
+}
+
+impl Filter {
+    fn matches(&self, item: &SearchResult, usage: &UsageStat, now: DateTime<Utc>) -> bool {
+        match self {
+            Filter::Text(text) => item.search_fields().iter().any(|f| normalize::fold(f).contains(text.as_str())),
+            Filter::Type(file_type) => item.file_type == *file_type,
+            Filter::Ext(ext) => Path::new(&item.id)
+                .extension()
+                .is_some_and(|e| e.to_string_lossy().to_lowercase() == *ext),
+            Filter::In(dir) => path_starts_with(Path::new(item.target_path().as_ref()), dir),
+            Filter::Used(cmp, n) => cmp.test(usage.use_count, *n),
+            Filter::Last(cmp, age) => match usage.last_used {
+                Some(t) => cmp.test(now - t, *age),
+                None => matches!(cmp, Cmp::Gt | Cmp::Ge),
+            },
+        }
+    }
+}
+
+// 路径是否位于 dir 之下。in: 比较的是条目指向的程序或文件 (SearchResult::target_path)：
+// 快捷方式与 .desktop 看它们启动的程序 (in:/opt/tool 能找到 Exec=/opt/tool/bin/tool 的应用)，其他条目看自身路径
+fn path_starts_with(path: &Path, dir: &Path) -> bool {
+    if cfg!(target_os = "windows") {
+        let path = path.to_string_lossy().to_lowercase();
+        let dir = dir.to_string_lossy().to_lowercase();
+        Path::new(&path).starts_with(Path::new(&dir))
+    } else {
+        path.starts_with(dir)
+    }
+}
+
+pub fn parse(query: &str) -> Result<ParsedQuery, QueryError> {
+    let mut parsed = ParsedQuery::default();
+    let mut text = Vec::new();
+
+    for token in tokenize(query)? {
+        let negated = token.negated;
+        let filter = if token.quoted_start {
+            Filter::Text(normalize::fold(&token.value))
+        } else if let Some(filter) = parse_filter(&token)? {
+            filter
+        } else if negated {
+            Filter::Text(normalize::fold(&token.value))
+        } else {
+            text.push(normalize::fold(&token.value));
+            continue;
+        };
+
+        // 短语同时参与打分
+        if let (Filter::Text(phrase), false) = (&filter, negated) {
+            text.push(phrase.clone());
+        }
+        parsed.terms.push(Term { negated, filter });
+    }
+
+    parsed.text = text.join(" ");
+    Ok(parsed)
+}
+
+struct Token {
+    value: String,       // 去掉了 "-" 前缀与引号
+    negated: bool,
+    quoted_start: bool,  // 以引号开头 (短语)
+    start: usize,
+    end: usize,
+}
+
+// 按空白切分，引号内的空白不切分
+fn tokenize(query: &str) -> Result<Vec<Token>, QueryError> {
+    let chars: Vec<char> = query.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        if chars[i].is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        let start = i;
+        // 单独的 "-" 是普通文本
+        let negated = chars[i] == '-' && chars.get(i + 1).is_some_and(|c| !c.is_whitespace());
+        if negated {
+            i += 1;
+        }
+        let quoted_start = chars.get(i) == Some(&'"');
+
+        let mut value = String::new();
+        let mut quote_start = None;
+        while i < chars.len() && (quote_start.is_some() || !chars[i].is_whitespace()) {
+            match chars[i] {
+                '"' if quote_start.is_some() => quote_start = None,
+                '"' => quote_start = Some(i),
+                c => value.push(c),
+            }
+            i += 1;
+        }
+
+        if let Some(q) = quote_start {
+            return Err(QueryError { message: "缺少右引号".into(), start: q, end: chars.len() });
+        }
+        if quoted_start && value.trim().is_empty() {
+            return Err(QueryError { message: "引号中的短语为空".into(), start, end: i });
+        }
+        tokens.push(Token { value, negated, quoted_start, start, end: i });
+    }
+    Ok(tokens)
+}
+
+fn parse_filter(token: &Token) -> Result<Option<Filter>, QueryError> {
+    let Some((key, value)) = token.value.split_once(':') else { return Ok(None) };
+    let key = key.to_lowercase();
+    if !FILTER_KEYS.contains(&key.as_str()) {
+        return Ok(None);
+    }
+
+    let error = |message: String| QueryError { message, start: token.start, end: token.end };
+    let value = value.trim();
+    if value.is_empty() {
+        return Err(error(format!("{}: 缺少值", key)));
+    }
+
+    let filter = match key.as_str() {
+        "type" => {
+            let lower = value.to_lowercase();
+            let file_type = TYPE_NAMES.iter()
+                .find(|(name, display)| *name == lower || display.to_lowercase() == lower)
+                .map(|(_, display)| *display)
+                .ok_or_else(|| {
+                    let names: Vec<&str> = TYPE_NAMES.iter().map(|(name, _)| *name).collect();
+                    error(format!("未知的类型 \"{}\"，可用: {}", value, names.join(", ")))
+                })?;
+            Filter::Type(file_type)
+        }
+        "ext" => Filter::Ext(value.trim_start_matches('.').to_lowercase()),
+        "in" => Filter::In(PathBuf::from(shellexpand::tilde(value).as_ref())),
+        "used" => {
+            let (cmp, n) = split_cmp(value);
+            let n = n.parse::<u32>().map_err(|_| error(format!("used: 需要次数，如 used:>5，而不是 \"{}\"", value)))?;
+            Filter::Used(cmp, n)
+        }
+        "last" => {
+            let (cmp, age) = split_cmp(value);
+            let age = parse_age(age).ok_or_else(|| error(format!("last: 需要时长，如 last:<7d (单位 m/h/d/w)，而不是 \"{}\"", value)))?;
+            Filter::Last(cmp, age)
+        }
+        _ => unreachable!(),
+    };
+    Ok(Some(filter))
+}
+
+fn split_cmp(value: &str) -> (Cmp, &str) {
+    for (prefix, cmp) in [(">=", Cmp::Ge), ("<=", Cmp::Le), (">", Cmp::Gt), ("<", Cmp::Lt), ("=", Cmp::Eq)] {
+        if let Some(rest) = value.strip_prefix(prefix) {
+            return (cmp, rest.trim());
+        }
+    }
+    (Cmp::Eq, value)
+}
+
+// "30m" "12h" "7d" "2w"，不带单位时按天计算
+fn parse_age(value: &str) -> Option<Duration> {
+    let split = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    let n = value[..split].parse::<u32>().ok()? as i64;
+    match &value[split..] {
+        "m" => Some(Duration::minutes(n)),
+        "h" => Some(Duration::hours(n)),
+        "" | "d" => Some(Duration::days(n)),
+        "w" => Some(Duration::weeks(n)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::PinyinReading;
+
+    fn item(id: &str, title: &str, file_type: &str) -> SearchResult {
+        SearchResult::new(id.into(), title.into(), file_type.into(), PinyinReading::default())
+    }
+
+    fn used(count: u32, days_ago: i64, now: DateTime<Utc>) -> UsageStat {
+        UsageStat { use_count: count, last_used: Some(now - Duration::days(days_ago)), ..UsageStat::default() }
+    }
+
+    #[test]
+    fn filters_and_negation() {
+        let now = Utc::now();
+        let none = UsageStat::default();
+        let deploy = item("/home/u/work/deploy.sh", "deploy", "Script");
+        let uninstall = item("/home/u/work/uninstall-deploy.sh", "Uninstall Deploy", "Script");
+        let code = item("/usr/share/applications/code.desktop", "Visual Studio Code", "Application");
+
+        let q = parse("type:script deploy -uninstall").unwrap();
+        assert_eq!(q.text, "deploy");
+        assert!(q.matches(&deploy, &none, now));
+        assert!(!q.matches(&uninstall, &none, now));
+        assert!(!q.matches(&code, &none, now));
+
+        let q = parse("EXT:.SH in:/home/u/work").unwrap();
+        assert_eq!(q.text, "");
+        assert!(q.matches(&deploy, &none, now));
+        assert!(!q.matches(&code, &none, now));
+        // in: 按路径组成部分比较，不是字符串前缀
+        assert!(!parse("in:/home/u/wo").unwrap().matches(&deploy, &none, now));
+        // 应用看启动的程序，快捷方式看解析出的目标
+        let mut tool = item("/usr/share/applications/tool.desktop", "Tool", "Application");
+        tool.action_type = "app".into();
+        tool.action_data = "/opt/tool/bin/tool %U".into();
+        assert!(parse("in:/opt/tool").unwrap().matches(&tool, &none, now));
+        assert!(!parse("in:/usr/share/applications").unwrap().matches(&tool, &none, now));
+        tool.action_data = "tool %U".into();
+        assert!(parse("in:/usr/share/applications").unwrap().matches(&tool, &none, now));
+        let mut link = item("/home/u/Desktop/deploy.lnk", "deploy", "Shortcut");
+        link.target = Some("/home/u/work/deploy.sh".into());
+        assert!(parse("in:/home/u/work").unwrap().matches(&link, &none, now));
+        assert!(!parse("-in:/home/u/work").unwrap().matches(&deploy, &none, now));
+    }
+
+    #[test]
+    fn usage_filters() {
+        let now = Utc::now();
+        let code = item("/opt/code/code", "Code", "Application");
+        assert!(parse("used:>5").unwrap().matches(&code, &used(7, 1, now), now));
+        assert!(!parse("used:>=8").unwrap().matches(&code, &used(7, 1, now), now));
+        assert!(parse("used:7").unwrap().matches(&code, &used(7, 1, now), now));
+        assert!(parse("last:<7d").unwrap().matches(&code, &used(1, 2, now), now));
+        assert!(!parse("last:<1w").unwrap().matches(&code, &used(1, 10, now), now));
+        // 从未使用视为无限久
+        assert!(parse("last:>30").unwrap().matches(&code, &UsageStat::default(), now));
+        assert!(!parse("last:<30d").unwrap().matches(&code, &UsageStat::default(), now));
+    }
+
+    #[test]
+    fn phrases_are_folded_and_scored() {
+        let now = Utc::now();
+        let none = UsageStat::default();
+        let cafe = item("/opt/cafe", "Café Manager Pro", "Application");
+
+        let q = parse("\"CAFE manager\" pro").unwrap();
+        assert_eq!(q.text, "cafe manager pro");
+        assert!(q.matches(&cafe, &none, now));
+        assert!(!parse("\"manager cafe\"").unwrap().matches(&cafe, &none, now));
+        assert!(!parse("-\"café\"").unwrap().matches(&cafe, &none, now));
+        assert!(parse("in:\"/opt\"").unwrap().matches(&cafe, &none, now));
+    }
+
+    #[test]
+    fn unknown_keys_and_lone_dash_are_text() {
+        assert_eq!(parse("http://example.com foo").unwrap().text, "http://example.com foo");
+        assert_eq!(parse("- foo").unwrap().text, "- foo");
+        assert!(!parse("http://x").unwrap().has_filters());
+        assert!(parse("type:\"System Tool\"").unwrap().has_filters());
+    }
+
+    #[test]
+    fn errors_point_at_the_offending_token() {
+        let e = parse("deploy used:>abc").unwrap_err();
+        assert_eq!((e.start, e.end), (7, 16));
+        let e = parse("foo \"bar").unwrap_err();
+        assert_eq!((e.start, e.end), (4, 8));
+        assert!(parse("type:bogus").unwrap_err().message.contains("script"));
+        assert!(parse("ext:").is_err());
+        assert!(parse("\"  \"").is_err());
+        assert!(parse("last:5y").is_err());
+        assert!(parse("last:99999999999d").is_err());
+    }
+}
@@ -0,0 +1,261 @@
+use crate::models::{AppSettings, PinyinReading, ScoreBreakdown, SearchResult, UsageStat, UserHabits};
+use crate::normalize;
+use crate::pinyin_input::PinyinInput;
+use crate::pinyin_match::match_syllables;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+
+// ==========================================
+// 打分
+// ==========================================
+// 每一项加分都记在 ScoreBreakdown 的对应字段里，search 只用总分，
+// explain_search 把各项返回给前端，排序不符合预期时可以看出是哪一项造成的
+
+pub struct Scorer<'a> {
+    matcher: SkimMatcherV2,
+    query: &'a str,
+    // 按空白切分为多个词，每个词都要匹配上 (顺序无关: "code visual" 找到 "Visual Studio Code")；
+    // 每个词的第一种拼法是原样，其后为双拼 / 模糊音拼法 (见 pinyin_input.rs)
+    spellings: Vec<Vec<String>>,
+    // 该查询下各条目的习惯权重 (AppID -> 分数)
+    habit_weights: HashMap<&'a str, i64>,
+    now: DateTime<Utc>,
+    half_life_days: f64,
+}
+
+impl<'a> Scorer<'a> {
+    // query 应已归一化 (见 normalize.rs)；settings 提供 frecency 的半衰期与拼音输入方式
+    pub fn new(query: &'a str, habits: &'a UserHabits, settings: &AppSettings, now: DateTime<Utc>) -> Self {
+        let input = PinyinInput::new(settings);
+        Self {
+            matcher: SkimMatcherV2::default(),
+            query,
+            spellings: query.split_whitespace().map(|token| input.spellings(token)).collect(),
+            habit_weights: habits.weights(query, now),
+            now,
+            half_life_days: settings.frecency_half_life_days,
+        }
+    }
+
+    pub fn matcher(&self) -> &SkimMatcherV2 {
+        &self.matcher
+    }
+
+    pub fn spellings(&self) -> &[Vec<String>] {
+        &self.spellings
+    }
+
+    // 随时间衰减的使用频率得分，空查询时直接按它排序
+    pub fn frecency(&self, usage: &UsageStat) -> i64 {
+        (usage.frecency(self.now, self.half_life_days) * 20.0).round() as i64
+    }
+
+    // 条目的得分；任意一个词匹配不上则不是结果
+    pub fn score(&self, item: &SearchResult, usage: &UsageStat) -> Option<ScoreBreakdown> {
+        let mut b = ScoreBreakdown::default();
+        let title_lower = item.normalized_title.as_str();
+        let file_name = normalize::fold(item.file_name());
+
+        // A/B. 逐词匹配，各词得分相加
+        // ----------------------------------------------------
+        for spellings in &self.spellings {
+            self.match_token(item, title_lower, &file_name, spellings, &mut b)?;
+        }
+
+        // C. 智能加权 (Heuristics)
+        // ----------------------------------------------------
+        let query = self.query;
+
+        // 1. 完全匹配奖励 (Exact Match)
+        if title_lower == query || item.pinyin_readings().any(|r| r.acronym == query) {
+            b.exact += 1000;
+        }
+        // 2. 前缀匹配奖励 (Starts With) - "code" 匹配 "Code.exe" 优于 "VS Code"
+        else if title_lower.starts_with(query) || item.pinyin_readings().any(|r| r.full.starts_with(query)) {
+            b.prefix += 200;
+        }
+
+        // 3. 历史记录权重 (最重要!)
+        // 包括以查询为前缀或为查询前缀的搜索词下的选择 (见 UserHabits)
+        b.habit += self.habit_weights.get(item.id.as_str()).copied().unwrap_or(0);
+
+        // 4. 通用热度加成 (frecency：最近常用的优先，很久以前的使用次数逐渐失去作用)
+        b.frecency += self.frecency(usage);
+
+        // 5. 长度惩罚 (Length Penalty)
+        // 名字越短通常越精确。 "Calc" 比 "OpenOffice Calc" 更好。
+        b.length_penalty -= item.title.len() as i64 * 2;
+
+        Some(b)
+    }
+
+    // 单个词的匹配得分，匹配不上时返回 None
+    fn match_token(&self, item: &SearchResult, title_lower: &str, file_name: &str, spellings: &[String], b: &mut ScoreBreakdown) -> Option<()> {
+        let token = spellings[0].as_str();
+        let mut matched = false;
+
+        // A. 基础 Fuzzy 匹配 (英文)
+        let mut fuzzy = 0;
+        if let Some(fuzzy_score) = self.matcher.fuzzy_match(title_lower, token) {
+            fuzzy = fuzzy_score;
+            b.fuzzy += fuzzy_score;
+            matched = true;
+        }
+
+        // B. 中文拼音 / 首字母缩写匹配，按字对齐 (多音字的任意一种读法都可以: "chongqing" 找到 "重庆地图")
+        if !matched || fuzzy < 50 {
+            let (pinyin, acronym) = pinyin_score(item.pinyin_readings(), spellings);
+            b.pinyin += pinyin;
+            b.acronym += acronym;
+            matched |= pinyin + acronym > 0;
+        }
+
+        // B2. 别名匹配 (e.g. 界面显示 "Files"，搜 "wjgl" 找到 "文件管理器")
+        if !matched {
+            for alias in &item.aliases {
+                let score = self.matcher.fuzzy_match(&normalize::fold(&alias.text), token).or_else(|| {
+                    let (pinyin, acronym) = pinyin_score(alias.pinyin_readings(), spellings);
+                    (pinyin + acronym > 0).then_some(pinyin + acronym)
+                });
+                if let Some(score) = score {
+                    b.alias += score;
+                    matched = true;
+                    break;
+                }
+            }
+        }
+
+        // B3. 关键词匹配 (e.g. 搜 "browser" 找到 Firefox)
+        if !matched && item.keywords.iter().any(|k| normalize::fold(k).contains(token)) {
+            b.keyword += 60;
+            matched = true;
+        }
+
+        // B4. 路径/文件名 兜底匹配
+        // 如果标题没匹配上，但文件名匹配上了 (e.g. 标题是"微信", 搜"WeChat.exe")
+        if file_name.contains(token) {
+            b.file_name += 50;
+            matched = true;
+        }
+
+        // B5. 路径匹配 (e.g. "deploy work" 中的 "work" 是 ~/work/deploy.sh 所在的目录)，权重低于标题
+        if !matched && normalize::fold(&item.target_path()).contains(token) {
+            b.path += 30;
+            matched = true;
+        }
+
+        if !matched {
+            return None;
+        }
+
+        // 词首匹配奖励："stu" 匹配 "Visual Studio" 的词首，优于散落在词中间的字母
+        if title_lower.split(|c: char| !c.is_alphanumeric()).any(|w| w.starts_with(token)) {
+            b.word_boundary += 40;
+        }
+
+        Some(())
+    }
+}
+
+// 全拼匹配 +80，只输入声母 +100 (e.g. "weixin" "weix" / "wx" 找到 "微信")，返回 (全拼得分, 首字母得分)。
+// 匹配的字每断开一次扣 30 分，连续的排在前面。
+// 依次尝试每种拼法，原样拼法优先；双拼 / 模糊音拼法匹配时少 20 分，拼写完全正确的条目排在前面
+fn pinyin_score<'r>(readings: impl Iterator<Item = &'r PinyinReading> + Clone, spellings: &[String]) -> (i64, i64) {
+    for (i, spelling) in spellings.iter().enumerate() {
+        let penalty = if i == 0 { 0 } else { 20 };
+        let best = readings.clone()
+            .filter_map(|reading| match_syllables(reading, spelling))
+            .map(|m| {
+                let score = (if m.abbreviated { 100 } else { 80 } - penalty - 30 * m.gaps as i64).max(10);
+                if m.abbreviated { (0, score) } else { (score, 0) }
+            })
+            .max_by_key(|&(pinyin, acronym)| pinyin + acronym);
+        if let Some(best) = best {
+            return best;
+        }
+    }
+    (0, 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::UsageStats;
+
+    fn item(path: &str, title: &str) -> SearchResult {
+        SearchResult::new(path.into(), title.into(), "File".into(), PinyinReading::default())
+    }
+
+    fn score(query: &str, item: &SearchResult) -> Option<ScoreBreakdown> {
+        let habits = UserHabits::default();
+        Scorer::new(query, &habits, &AppSettings::default(), Utc::now()).score(item, UsageStat::unused())
+    }
+
+    #[test]
+    fn tokens_match_directories_in_the_path() {
+        let script = item("/home/u/work/deploy.sh", "deploy.sh");
+        let b = score("deploy work", &script).unwrap();
+        assert_eq!(b.path, 30);
+        assert!(score("deploy other", &script).is_none());
+        // 标题匹配不计路径分
+        assert_eq!(score("deploy", &script).unwrap().path, 0);
+    }
+
+    #[test]
+    fn path_matches_rank_below_title_matches() {
+        let notes = item("/home/u/notes/work log.txt", "work log.txt");
+        let script = item("/home/u/work/deploy.sh", "deploy.sh");
+        assert!(score("work", &notes).unwrap().total() > score("work", &script).unwrap().total());
+    }
+
+    #[test]
+    fn apps_match_the_program_path() {
+        let mut app = item("/usr/share/applications/tool.desktop", "Tool");
+        app.action_type = "app".into();
+        app.action_data = "/opt/acme/bin/tool --new-window".into();
+        assert_eq!(app.target_path(), "/opt/acme/bin/tool");
+        assert_eq!(score("tool acme", &app).unwrap().path, 30);
+
+        app.action_data = "tool %U".into();
+        assert_eq!(app.target_path(), app.id);
+        let mut link = item("C:\\Users\\u\\Desktop\\Tool.lnk", "Tool");
+        link.target = Some("D:\\Apps\\Acme\\tool.exe".into());
+        assert_eq!(link.target_path(), "D:\\Apps\\Acme\\tool.exe");
+    }
+
+    #[test]
+    fn breakdown_components_add_up_to_the_total() {
+        let code = item("/usr/bin/code", "Code");
+        let mut habits = UserHabits::default();
+        habits.record("code", &code.id);
+        let mut usage = UsageStats::default();
+        usage.record(&code.id);
+
+        let b = Scorer::new("code", &habits, &AppSettings::default(), Utc::now()).score(&code, usage.get(&code.id)).unwrap();
+        assert!(b.fuzzy > 0);
+        assert!(b.habit > 0);
+        assert_eq!((b.exact, b.prefix), (1000, 0));
+        assert_eq!(b.word_boundary, 40);
+        assert_eq!(b.file_name, 50);
+        assert_eq!(b.frecency, 20);
+        assert_eq!(b.length_penalty, -8);
+        assert_eq!(b.total(), b.fuzzy + b.habit + 1000 + 40 + 50 + 20 - 8);
+        // 未用过的条目只差习惯与热度两项
+        let unused = score("code", &code).unwrap();
+        assert_eq!(unused.total(), b.total() - b.habit - b.frecency);
+    }
+
+    #[test]
+    fn any_reading_of_a_polyphonic_title_matches() {
+        let (pinyin, variants) = crate::transliterate::Transliteration::new(&["zh".to_string()]).readings("重庆地图");
+        let mut map = SearchResult::new("/maps".into(), "重庆地图".into(), "Application".into(), pinyin);
+        map.pinyin_variants = variants;
+        assert_eq!(score("chongqing", &map).unwrap().pinyin, 80);
+        assert_eq!(score("zhongqing", &map).unwrap().pinyin, 80);
+        // 其他读法的首字母同样算完全匹配
+        assert_eq!(score("cqdt", &map).unwrap().exact, 1000);
+        assert!(score("chongqingx", &map).is_none());
+    }
+}
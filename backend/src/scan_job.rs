@@ -0,0 +1,187 @@
+use crate::models::ScanProgressEvent;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+// ==========================================
+// 扫描任务管理
+// ==========================================
+// 同一时间最多只有一个全量扫描：扫描进行中再次请求时不会并发启动第二个，
+// 而是记下"需要重扫"，当前扫描结束后再补一次 (例如扫描途中修改了扫描规则)
+
+// 最多保留的错误条数 (整盘扫描时权限错误可能成千上万)
+const MAX_REPORTED_ERRORS: usize = 50;
+
+// 一次扫描的进度，扫描线程更新，进度上报线程读取
+#[derive(Default)]
+pub struct ScanProgress {
+    cancelled: AtomicBool,
+    roots_total: AtomicUsize,
+    roots_completed: AtomicUsize,
+    dirs_visited: AtomicUsize,
+    files_visited: AtomicUsize,
+    items_found: AtomicUsize,
+    error_count: AtomicUsize,
+    errors: Mutex<Vec<String>>,
+}
+
+impl ScanProgress {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    pub fn set_roots_total(&self, n: usize) {
+        self.roots_total.store(n, Ordering::Relaxed);
+    }
+
+    pub fn root_completed(&self) {
+        self.roots_completed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn dir_visited(&self, items: usize) {
+        self.dirs_visited.fetch_add(1, Ordering::Relaxed);
+        self.items_found.fetch_add(items, Ordering::Relaxed);
+    }
+
+    pub fn files_visited(&self, n: usize) {
+        self.files_visited.fetch_add(n, Ordering::Relaxed);
+    }
+
+    pub fn record_error(&self, message: String) {
+        self.error_count.fetch_add(1, Ordering::Relaxed);
+        let mut errors = self.errors.lock().unwrap_or_else(|e| e.into_inner());
+        if errors.len() < MAX_REPORTED_ERRORS {
+            errors.push(message);
+        }
+    }
+
+    pub fn error_count(&self) -> usize {
+        self.error_count.load(Ordering::Relaxed)
+    }
+
+    pub fn errors(&self) -> Vec<String> {
+        self.errors.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    pub fn snapshot(&self) -> ScanProgressEvent {
+        ScanProgressEvent {
+            roots_total: self.roots_total.load(Ordering::Relaxed),
+            roots_completed: self.roots_completed.load(Ordering::Relaxed),
+            dirs_visited: self.dirs_visited.load(Ordering::Relaxed),
+            files_visited: self.files_visited.load(Ordering::Relaxed),
+            items_found: self.items_found.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Default)]
+struct JobState {
+    current: Option<Arc<ScanProgress>>,
+    rescan_requested: bool,
+}
+
+#[derive(Default)]
+pub struct ScanJobs {
+    state: Mutex<JobState>,
+}
+
+impl ScanJobs {
+    fn lock(&self) -> std::sync::MutexGuard<'_, JobState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    // 开始一次扫描；已有扫描在进行时返回 None，并在其结束后自动重扫一次
+    pub fn begin(&self) -> Option<Arc<ScanProgress>> {
+        let mut state = self.lock();
+        if state.current.is_some() {
+            state.rescan_requested = true;
+            return None;
+        }
+        let progress = Arc::new(ScanProgress::default());
+        state.current = Some(progress.clone());
+        Some(progress)
+    }
+
+    // 当前扫描结束。若期间有新的扫描请求，直接返回下一次扫描的进度 (任务线程继续执行)
+    pub fn finish(&self) -> Option<Arc<ScanProgress>> {
+        let mut state = self.lock();
+        if std::mem::take(&mut state.rescan_requested) {
+            let progress = Arc::new(ScanProgress::default());
+            state.current = Some(progress.clone());
+            Some(progress)
+        } else {
+            state.current = None;
+            None
+        }
+    }
+
+    // 取消正在进行的扫描 (连同排队的重扫)；没有扫描时返回 false
+    pub fn cancel(&self) -> bool {
+        let mut state = self.lock();
+        state.rescan_requested = false;
+        match &state.current {
+            Some(progress) => {
+                progress.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn current_progress(&self) -> Option<ScanProgressEvent> {
+        self.lock().current.as_ref().map(|p| p.snapshot())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_one_scan_runs_and_requests_during_it_are_merged() {
+        let jobs = ScanJobs::default();
+        let first = jobs.begin().unwrap();
+        assert!(jobs.begin().is_none());
+        assert!(jobs.begin().is_none());
+        first.dir_visited(3);
+        assert_eq!(jobs.current_progress().unwrap().items_found, 3);
+
+        // 期间的两次请求合并为一次重扫，使用新的进度
+        let rescan = jobs.finish().unwrap();
+        assert!(!Arc::ptr_eq(&first, &rescan));
+        assert_eq!(jobs.current_progress().unwrap().items_found, 0);
+        assert!(jobs.begin().is_none());
+        assert!(jobs.finish().is_some());
+        assert!(jobs.finish().is_none());
+        assert!(jobs.current_progress().is_none());
+        assert!(jobs.begin().is_some());
+    }
+
+    #[test]
+    fn cancel_stops_the_scan_and_drops_the_queued_rescan() {
+        let jobs = ScanJobs::default();
+        assert!(!jobs.cancel());
+
+        let progress = jobs.begin().unwrap();
+        assert!(jobs.begin().is_none());
+        assert!(jobs.cancel());
+        assert!(progress.is_cancelled());
+        assert!(jobs.finish().is_none());
+        // 下一次扫描不受影响
+        assert!(!jobs.begin().unwrap().is_cancelled());
+    }
+
+    #[test]
+    fn errors_are_counted_but_only_the_first_are_kept() {
+        let progress = ScanProgress::default();
+        for i in 0..MAX_REPORTED_ERRORS + 10 {
+            progress.record_error(format!("dir {i}"));
+        }
+        assert_eq!(progress.error_count(), MAX_REPORTED_ERRORS + 10);
+        assert_eq!(progress.errors().len(), MAX_REPORTED_ERRORS);
+        assert_eq!(progress.errors()[0], "dir 0");
+    }
+}
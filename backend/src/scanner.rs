@@ -1,10 +1,16 @@
-use crate::models::SearchResult;
-use jwalk::{DirEntry, WalkDir};
-use pinyin::ToPinyin;
+use crate::desktop_entry::{self, DesktopEntry};
+use crate::ignore_rules::{self, IgnoreStack};
+use crate::models::{Alias, AppSettings, DirRecord, RootScanSummary, ScanCache, ScanRule, ScanSummary, SearchResult};
+use crate::scan_job::ScanProgress;
+use crate::shortcut::Shortcut;
+use crate::transliterate::Transliteration;
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
 use rayon::prelude::*;
 use std::collections::{HashMap, HashSet};
 use std::ffi::OsStr;
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime};
 
 #[cfg(target_os = "windows")]
 use winreg::enums::*;
@@ -14,10 +20,12 @@ use winreg::RegKey;
 // ==========================================
 // 1. 更加智能的黑名单过滤
 // ==========================================
+// 以下为默认值，实际使用的是 AppSettings 中可由用户修改的列表
 
 // 文件夹黑名单：增加更多开发和系统缓存目录
-const CRITICAL_FOLDER_BLACKLIST: &[&str] = &[
-    "node_modules", "bower_components", "target", "build", "dist", "vendor", // 开发相关
+// target / build / dist 这类构建产物目录交给项目自己的 .gitignore 判断，不按名字屏蔽
+pub const DEFAULT_FOLDER_BLACKLIST: &[&str] = &[
+    "node_modules", "bower_components", "vendor", // 开发相关
     ".git", ".svn", ".hg", ".idea", ".vscode", ".settings", // 版本控制与IDE
     "__pycache__", "site-packages", "gems", "cargo", // 语言包库
     "$recycle.bin", "system volume information", "msocache", "config.msi", // Windows 系统
@@ -25,15 +33,20 @@ const CRITICAL_FOLDER_BLACKLIST: &[&str] = &[
 ];
 
 // 文件名关键词黑名单：过滤掉卸载程序、帮助文档、升级程序
-const FILENAME_NOISE_KEYWORDS: &[&str] = &[
+pub const DEFAULT_NOISE_KEYWORDS: &[&str] = &[
     "uninstall", "uninst", "setup", "install", "update", "helper", 
     "config", "readme", "license", "eula", "vcredist", "dxsetup"
 ];
 
-fn is_critical_garbage_folder(name: &str) -> bool {
-    // 忽略大小写比较
-    CRITICAL_FOLDER_BLACKLIST.iter().any(|&bad| name.eq_ignore_ascii_case(bad))
-}
+// 规则未指定 extensions 时允许的扩展名 (小写)
+#[cfg(target_os = "windows")]
+const DEFAULT_EXTENSIONS: &[&str] = &["lnk", "exe", "bat", "cmd", "com", "msc"];
+#[cfg(target_os = "macos")]
+const DEFAULT_EXTENSIONS: &[&str] = &["app", "prefpane"];
+#[cfg(target_os = "linux")]
+const DEFAULT_EXTENSIONS: &[&str] = &["desktop", "sh", "appimage"];
+#[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+const DEFAULT_EXTENSIONS: &[&str] = &[];
 
 fn is_garbage_path(path_str: &str) -> bool {
     // 路径过长通常是自动生成的乱七八糟的东西
@@ -41,53 +54,67 @@ fn is_garbage_path(path_str: &str) -> bool {
     false
 }
 
-// 判断是否为噪音文件（如 uninstall.exe）
-fn is_noise_file(name: &str) -> bool {
-    let lower = name.to_lowercase();
-    FILENAME_NOISE_KEYWORDS.iter().any(|&bad| lower.contains(bad))
-}
-
-fn is_launchable(entry: &DirEntry<((), ())>) -> bool {
-    if !entry.file_type().is_file() { return false; }
-    
-    let path = entry.path();
+fn is_launchable(path: &Path, rule: &CompiledRule, plan: &ScanPlan) -> bool {
     // 1. 扩展名检查
     let ext = match path.extension().and_then(OsStr::to_str) {
         Some(e) => e.to_ascii_lowercase(),
         None => return false,
     };
 
-    let is_valid_ext = match ext.as_str() {
-        #[cfg(target_os = "windows")]
-        "lnk" | "exe" | "bat" | "cmd" | "com" | "msc" => true,
-        #[cfg(target_os = "macos")]
-        "app" | "prefPane" => true, 
-        #[cfg(target_os = "linux")]
-        "desktop" | "sh" | "AppImage" => true,
-        _ => false 
-    };
-
-    if !is_valid_ext { return false; }
+    if !rule.extensions.contains(&ext) { return false; }
 
     // 2. 噪音文件检查 (仅针对非快捷方式)
-    // 如果是 .lnk 快捷方式，通常是用户特意创建的，不应该被过滤
-    if ext != "lnk" {
+    // .lnk 快捷方式通常是用户特意创建的，.desktop 有自己的 NoDisplay 标记，都不应该被过滤
+    if ext != "lnk" && ext != "desktop" {
         if let Some(stem) = path.file_stem().and_then(OsStr::to_str) {
-            if is_noise_file(stem) { return false; }
+            if plan.is_noise_file(stem) { return false; }
         }
     }
 
     true
 }
 
+// Unix 下 PATH 中的命令行工具大多没有扩展名 (git, htop, python3)
+// 依据执行权限位判断，并校验文件头 (ELF / Mach-O / shebang)，排除误设了 x 位的数据文件
+#[cfg(unix)]
+fn is_unix_command(path: &Path, meta: &std::fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    if meta.permissions().mode() & 0o111 == 0 {
+        return false;
+    }
+    has_executable_magic(path)
+}
+
+#[cfg(not(unix))]
+fn is_unix_command(_path: &Path, _meta: &std::fs::Metadata) -> bool {
+    false
+}
+
+#[cfg(unix)]
+fn has_executable_magic(path: &Path) -> bool {
+    use std::io::Read;
+
+    let mut header = [0u8; 4];
+    let read = std::fs::File::open(path).and_then(|mut f| f.read_exact(&mut header));
+    if read.is_err() {
+        return false;
+    }
+    header.starts_with(b"#!")
+        || header == *b"\x7fELF"
+        // Mach-O (32/64 位，两种字节序) 与 Universal Binary
+        || matches!(header, [0xfe, 0xed, 0xfa, 0xce | 0xcf] | [0xce | 0xcf, 0xfa, 0xed, 0xfe] | [0xca, 0xfe, 0xba, 0xbe])
+}
+
 // ==========================================
 // 2. 辅助工具 (拼音生成与名称清洗)
 // ==========================================
 
+// ext 为小写的扩展名
 fn get_file_type_display(ext: &str) -> String {
     match ext {
         "lnk" => "Shortcut",
-        "exe" | "app" | "AppImage" => "Application",
+        "exe" | "app" | "appimage" => "Application",
         "bat" | "cmd" | "sh" => "Script",
         "msc" => "System Tool",
         _ => "File",
@@ -105,29 +132,68 @@ fn clean_filename(path: &Path) -> String {
         .to_string()
 }
 
-fn generate_pinyin_data(name: &str) -> (String, String) {
-    let mut full = String::with_capacity(name.len() * 2);
-    let mut abbr = String::with_capacity(name.len());
-    // 优化：只转换中文字符，英文字符直接追加，提升性能
-    for c in name.chars() {
-        if c.is_ascii() {
-            let lower = c.to_ascii_lowercase();
-            full.push(lower);
-            abbr.push(lower);
-        } else if let Some(p) = c.to_pinyin() {
-            let plain = p.plain();
-            full.push_str(plain);
-            if let Some(first) = plain.chars().next() {
-                abbr.push(first);
-            }
-        } else {
-            // 处理其他语言或符号
-            let lower = c.to_lowercase().to_string();
-            full.push_str(&lower);
-            abbr.push_str(&lower);
-        }
+// PATH 中的命令：标题保留完整文件名 (python3.11 不能被截成 python3)，在终端中运行
+fn command_to_result(path: &Path, translit: &Transliteration) -> SearchResult {
+    let path_str = path.to_string_lossy().to_string();
+    let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+    let (pinyin, variants) = translit.readings(&name);
+
+    let mut result = SearchResult::new(path_str.clone(), name, "Command".into(), pinyin);
+    result.pinyin_variants = variants;
+    result.action_type = "command".into();
+    result.action_data = desktop_entry::join_exec(&[path_str]);
+    result
+}
+
+// Windows .lnk 快捷方式：解析真实目标，启动时仍然打开 .lnk 本身 (保留参数、工作目录、管理员权限等设置)
+fn shortcut_to_result(path: &Path, translit: &Transliteration) -> SearchResult {
+    let name = clean_filename(path);
+    let (pinyin, variants) = translit.readings(&name);
+    let mut result = SearchResult::new(path.to_string_lossy().to_string(), name, "Shortcut".into(), pinyin);
+    result.pinyin_variants = variants;
+
+    if let Some(link) = Shortcut::parse_file(path) {
+        result.target = link.target;
+        result.arguments = link.arguments;
+        result.working_dir = link.working_dir;
+        result.icon = link.icon_location;
+        result.description = link.description;
+    }
+    result
+}
+
+// Linux .desktop 文件：使用 Name / Exec 等字段，而不是文件名
+// 显示名称按 locale 回退链选择，其余语言的名称作为别名同样可以搜到
+fn desktop_entry_to_result(path: &Path, current_desktops: &[String], locales: &[String], translit: &Transliteration) -> Option<SearchResult> {
+    let entry = DesktopEntry::parse_file(path)?;
+    if !entry.should_show(current_desktops) {
+        return None;
     }
-    (full, abbr)
+
+    let path_str = path.to_string_lossy().to_string();
+    let args = entry.expand_exec(&path_str, locales)?;
+    let title = entry.display_name(locales).to_string();
+    let (pinyin, variants) = translit.readings(&title);
+
+    let aliases = entry.all_names().into_iter()
+        .filter(|name| *name != title)
+        .map(|name| {
+            let (pinyin, pinyin_variants) = translit.readings(name);
+            Alias { text: name.to_string(), pinyin, pinyin_variants }
+        })
+        .collect();
+
+    let mut result = SearchResult::new(path_str, title, "Application".into(), pinyin);
+    result.pinyin_variants = variants;
+    // Terminal=true 的程序 (如 htop.desktop) 需要在终端中运行
+    result.action_type = if entry.terminal { "command".into() } else { "app".into() };
+    result.action_data = desktop_entry::join_exec(&args);
+    result.keywords = entry.all_keywords();
+    result.aliases = aliases;
+    result.description = entry.comment;
+    result.icon = entry.icon;
+    result.categories = entry.categories;
+    Some(result)
 }
 
 // ==========================================
@@ -148,6 +214,42 @@ fn get_path_env_dirs() -> Vec<PathBuf> {
     paths
 }
 
+// 开始菜单 (所有用户 + 当前用户)
+#[cfg(target_os = "windows")]
+fn get_start_menu_dirs() -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    if let Ok(pd) = std::env::var("ProgramData") {
+        paths.push(PathBuf::from(pd).join(r"Microsoft\Windows\Start Menu\Programs"));
+    }
+    if let Some(roaming) = dirs::config_dir() {
+        paths.push(roaming.join(r"Microsoft\Windows\Start Menu\Programs"));
+    }
+    paths
+}
+
+// XDG 应用目录: $XDG_DATA_HOME/applications 与 $XDG_DATA_DIRS 中每一项的 applications
+// (Flatpak / Snap 导出的 .desktop 也通过 XDG_DATA_DIRS 暴露)
+#[cfg(not(target_os = "windows"))]
+fn get_xdg_application_dirs() -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    match std::env::var_os("XDG_DATA_HOME").filter(|v| !v.is_empty()) {
+        Some(data_home) => paths.push(PathBuf::from(data_home).join("applications")),
+        None => {
+            if let Some(home) = dirs::home_dir() {
+                paths.push(home.join(".local/share/applications"));
+            }
+        }
+    }
+    let data_dirs = std::env::var_os("XDG_DATA_DIRS")
+        .filter(|v| !v.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".into());
+    for dir in std::env::split_paths(&data_dirs) {
+        paths.push(dir.join("applications"));
+    }
+    paths.retain(|p| p.exists());
+    paths
+}
+
 #[cfg(target_os = "windows")]
 fn get_registry_installed_paths() -> Vec<PathBuf> {
     let mut paths = Vec::new();
@@ -176,178 +278,748 @@ fn get_registry_installed_paths() -> Vec<PathBuf> {
     paths
 }
 
+// 数据盘 D: 到 Z: (查漏补缺)
+// 注意：全盘扫描非常慢，默认规则限制了深度，也可以在设置中移除 @drives 规则
+#[cfg(target_os = "windows")]
+fn get_drive_roots() -> Vec<PathBuf> {
+    (b'D'..=b'Z')
+        .map(|drive_char| PathBuf::from(format!("{}:\\", drive_char as char)))
+        .filter(|p| p.exists())
+        .collect()
+}
+
 // ==========================================
-// 4. 扫描逻辑主体
+// 3.1 扫描规则
 // ==========================================
-pub fn scan_applications() -> Vec<SearchResult> {
-    // 使用 HashSet 自动去重路径，避免重复扫描同一个目录
-    let mut scan_roots = HashSet::new();
 
-    // -----------------------------------------------------------
+// 默认规则即原先硬编码的扫描范围。差异化深度策略：
+// - 用户目录（下载/文档）：杂文件多，限制浅层扫描
+// - 根驱动器 (D:\)：防止进入深层备份目录，限制中等深度
+// - 开始菜单/PATH：本身就是存放程序的，允许较深
+pub fn default_scan_rules() -> Vec<ScanRule> {
+    let mut rules = Vec::new();
+
     // A. 关键系统路径 (高纯度数据源)
-    // -----------------------------------------------------------
     #[cfg(target_os = "windows")]
     {
-        // 1. 开始菜单 (最重要)
-        if let Ok(pd) = std::env::var("ProgramData") {
-            scan_roots.insert(PathBuf::from(pd).join(r"Microsoft\Windows\Start Menu\Programs"));
+        rules.push(ScanRule::new("@start_menu", 30));
+        rules.push(ScanRule::new("@desktop", 30));
+        rules.push(ScanRule::new("@documents", 4));
+        rules.push(ScanRule::new("@downloads", 4));
+        // 注册表记录的安装位置
+        rules.push(ScanRule::new("@registry", 30));
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        rules.push(ScanRule::new("/Applications", 30));
+        rules.push(ScanRule::new("@applications", 30));
+    }
+
+    // B. PATH 环境变量：覆盖 git.exe, code.exe, node.exe 等 CLI 工具
+    // Unix 下还会按执行权限收录无扩展名的命令 (见 is_unix_command)
+    let mut path_rule = ScanRule::new("@path", 30);
+    path_rule.commands = true;
+    rules.push(path_rule);
+
+    // C. 磁盘全盘扫描
+    #[cfg(target_os = "windows")]
+    rules.push(ScanRule::new("@drives", 8));
+
+    rules
+}
+
+// 规则路径展开为实际存在的目录：内置来源 (@xxx) 或普通路径 (展开 ~ 与环境变量)
+fn expand_rule_path(path: &str) -> Vec<PathBuf> {
+    let mut paths = match path {
+        #[cfg(target_os = "windows")]
+        "@start_menu" => get_start_menu_dirs(),
+        #[cfg(target_os = "windows")]
+        "@registry" => get_registry_installed_paths(),
+        #[cfg(target_os = "windows")]
+        "@drives" => get_drive_roots(),
+        #[cfg(not(target_os = "windows"))]
+        "@applications" => get_xdg_application_dirs(),
+        "@desktop" => dirs::desktop_dir().into_iter().collect(),
+        "@documents" => dirs::document_dir().into_iter().collect(),
+        "@downloads" => dirs::download_dir().into_iter().collect(),
+        "@path" => get_path_env_dirs(),
+        _ if path.starts_with('@') => {
+            eprintln!("Unknown scan source on this platform: {}", path);
+            Vec::new()
         }
-        if let Some(roaming) = dirs::config_dir() {
-            scan_roots.insert(roaming.join(r"Microsoft\Windows\Start Menu\Programs"));
+        _ => match shellexpand::full(path) {
+            Ok(expanded) => vec![PathBuf::from(expanded.as_ref())],
+            Err(e) => {
+                eprintln!("Invalid scan path {}: {}", path, e);
+                Vec::new()
+            }
+        },
+    };
+    paths.retain(|p| p.exists());
+    paths
+}
+
+// 编译后的规则
+struct CompiledRule {
+    max_depth: usize,
+    include: Option<GlobSet>,
+    exclude: GlobSet,
+    extensions: HashSet<String>,
+    commands: bool,
+    follow_symlinks: bool,
+    ignore_files: bool,
+}
+
+impl CompiledRule {
+    fn new(rule: &ScanRule) -> Self {
+        let extensions: HashSet<String> = if rule.extensions.is_empty() {
+            DEFAULT_EXTENSIONS.iter().map(|e| e.to_string()).collect()
+        } else {
+            rule.extensions.iter().map(|e| e.trim_start_matches('.').to_ascii_lowercase()).collect()
+        };
+        let include = (!rule.include.is_empty()).then(|| build_globset(&rule.include));
+        Self {
+            max_depth: rule.max_depth,
+            include,
+            exclude: build_globset(&rule.exclude),
+            extensions,
+            commands: rule.commands,
+            follow_symlinks: rule.follow_symlinks,
+            ignore_files: rule.ignore_files,
+        }
+    }
+
+    fn is_excluded(&self, path: &Path) -> bool {
+        self.exclude.is_match(path)
+    }
+
+    fn is_included(&self, path: &Path) -> bool {
+        self.include.as_ref().is_none_or(|set| set.is_match(path))
+    }
+}
+
+// glob 匹配完整路径；"dir/**" 同时匹配目录本身，这样扫描时可以直接剪掉整个目录
+// 无效的 glob 只打印警告并忽略，不影响其余规则
+fn build_globset(patterns: &[String]) -> GlobSet {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let pattern = shellexpand::tilde(pattern.trim()).to_string();
+        let mut variants = vec![pattern.clone()];
+        if let Some(dir) = pattern.strip_suffix("/**").filter(|d| !d.is_empty()) {
+            variants.push(dir.to_string());
         }
-        
-        // 2. 桌面
-        if let Some(desktop) = dirs::desktop_dir() {
-            scan_roots.insert(desktop);
+        for variant in variants {
+            match GlobBuilder::new(&variant).case_insensitive(cfg!(target_os = "windows")).build() {
+                Ok(glob) => { builder.add(glob); }
+                Err(e) => eprintln!("Invalid glob {}: {}", variant, e),
+            }
         }
+    }
+    builder.build().unwrap_or_else(|e| {
+        eprintln!("Failed to build glob set: {}", e);
+        GlobSet::empty()
+    })
+}
+
+// 规则展开后的一个扫描根目录
+struct ScanRoot {
+    path: PathBuf,
+    source: String, // 规则中的原始路径 (如 "@path")
+    rule: Arc<CompiledRule>,
+}
+
+// 由设置生成的扫描计划：全量扫描、文件监听与增量更新共用
+pub struct ScanPlan {
+    roots: Vec<ScanRoot>,
+    folder_blacklist: Vec<String>,
+    noise_keywords: Vec<String>,
+    // 名称的拼音 / 罗马字读法 (按设置启用的语言)
+    transliteration: Transliteration,
+    // 扫描配置本身，变化时旧的目录缓存作废
+    fingerprint: String,
+}
 
-        // 3. 用户常用目录 (Downloads, Documents) - 限制深度扫描
-        if let Some(docs) = dirs::document_dir() { scan_roots.insert(docs); }
-        if let Some(dl) = dirs::download_dir() { scan_roots.insert(dl); }
+impl ScanPlan {
+    pub fn new(settings: &AppSettings) -> Self {
+        let mut roots = Vec::new();
+        // 同一目录出现在多条规则中时只扫描一次，以靠前的规则为准
+        let mut seen = HashSet::new();
+        for rule in &settings.scan_rules {
+            let compiled = Arc::new(CompiledRule::new(rule));
+            for path in expand_rule_path(&rule.path) {
+                if seen.insert(path.clone()) {
+                    roots.push(ScanRoot { path, source: rule.path.clone(), rule: compiled.clone() });
+                }
+            }
+        }
 
-        // 4. 注册表记录的安装位置
-        for p in get_registry_installed_paths() {
-            scan_roots.insert(p);
+        Self {
+            roots,
+            folder_blacklist: settings.folder_blacklist.clone(),
+            noise_keywords: settings.noise_keywords.iter().map(|k| k.to_lowercase()).collect(),
+            transliteration: Transliteration::new(&settings.transliteration),
+            fingerprint: format!(
+                "rules={:?};blacklist={:?};noise={:?};transliteration={:?}",
+                settings.scan_rules, settings.folder_blacklist, settings.noise_keywords, settings.transliteration
+            ),
         }
     }
 
-    #[cfg(not(target_os = "windows"))]
-    {
-        scan_roots.insert(PathBuf::from("/Applications"));
-        scan_roots.insert(PathBuf::from("/usr/share/applications"));
-        if let Some(home) = dirs::home_dir() {
-             scan_roots.insert(home.join(".local/share/applications"));
+    fn is_blacklisted_folder(&self, name: &str) -> bool {
+        // 忽略大小写比较
+        self.folder_blacklist.iter().any(|bad| name.eq_ignore_ascii_case(bad))
+    }
+
+    // 判断是否为噪音文件（如 uninstall.exe）
+    fn is_noise_file(&self, name: &str) -> bool {
+        let lower = name.to_lowercase();
+        self.noise_keywords.iter().any(|bad| lower.contains(bad.as_str()))
+    }
+
+    // 包含 path 的最深的扫描根目录
+    fn root_for(&self, path: &Path) -> Option<&ScanRoot> {
+        self.roots.iter()
+            .filter(|r| path.starts_with(&r.path))
+            .max_by_key(|r| r.path.as_os_str().len())
+    }
+}
+
+// ==========================================
+// 4. 扫描逻辑主体
+// ==========================================
+// 按扫描计划扫描所有根目录。cache 为上次扫描保存的目录记录，mtime 未变化的目录直接复用；
+// 返回去重后的结果、本次扫描的目录记录 (供下次增量扫描使用) 以及扫描摘要
+// 扫描被取消时 summary.cancelled 为 true，结果不完整，不应使用
+pub fn scan_applications(plan: &ScanPlan, cache: &ScanCache, progress: &ScanProgress) -> (Vec<SearchResult>, ScanCache, ScanSummary) {
+    let start = Instant::now();
+    let ctx = ScanContext::new(plan, progress);
+    progress.set_roots_total(plan.roots.len());
+    ctx.visited_links.lock().unwrap_or_else(|e| e.into_inner())
+        .extend(plan.roots.iter().filter_map(|r| r.path.canonicalize().ok()));
+
+    // 环境 (如切换了系统语言) 或扫描规则变化时旧缓存中的结果已不可信，整体作废
+    let fingerprint = ctx.fingerprint();
+    let empty_cache = ScanCache::default();
+    let old_cache = if cache.fingerprint == fingerprint { cache } else { &empty_cache };
+
+    // 并行扫描执行
+    let per_root: Vec<DirScan> = plan.roots.par_iter()
+        .map(|root| {
+            // 与 ripgrep 相同，根目录的上级目录中的忽略文件同样生效
+            let parent_ignores = match root.path.parent() {
+                Some(parent) if root.rule.ignore_files => IgnoreStack::for_dir(parent),
+                _ => IgnoreStack::default(),
+            };
+            let scan = scan_dir(&root.path, 0, &root.rule, &parent_ignores, &ctx, old_cache);
+            progress.root_completed();
+            scan
+        })
+        .collect();
+
+    let mut summary = ScanSummary {
+        cancelled: progress.is_cancelled(),
+        roots: plan.roots.iter().zip(&per_root)
+            .map(|(root, scan)| RootScanSummary {
+                path: root.path.clone(),
+                items: scan.items.len(),
+                dirs: scan.records.len(),
+            })
+            .collect(),
+        error_count: progress.error_count(),
+        errors: progress.errors(),
+        ..ScanSummary::default()
+    };
+    if summary.cancelled {
+        summary.duration_ms = start.elapsed().as_millis() as u64;
+        return (Vec::new(), ScanCache::default(), summary);
+    }
+
+    let scanned = per_root.into_iter().fold(DirScan::default(), DirScan::merge);
+    let new_cache = ScanCache {
+        fingerprint,
+        dirs: scanned.records.into_iter().collect(),
+    };
+    let apps = deduplicate(scanned.items);
+    summary.total_items = apps.len();
+    summary.duration_ms = start.elapsed().as_millis() as u64;
+    (apps, new_cache, summary)
+}
+
+// 一次扫描中所有根目录共享的环境
+struct ScanContext<'a> {
+    plan: &'a ScanPlan,
+    progress: &'a ScanProgress,
+    current_desktops: Vec<String>,
+    locales: Vec<String>,
+    // 已进入过的符号链接目标 (规范化路径)，防止 /usr/bin/X11 -> /usr/bin 这类循环
+    visited_links: Mutex<HashSet<PathBuf>>,
+}
+
+impl<'a> ScanContext<'a> {
+    fn new(plan: &'a ScanPlan, progress: &'a ScanProgress) -> Self {
+        Self {
+            plan,
+            progress,
+            current_desktops: desktop_entry::current_desktops(),
+            locales: desktop_entry::current_locales(),
+            visited_links: Mutex::new(HashSet::new()),
         }
     }
 
-    // -----------------------------------------------------------
-    // B. PATH 环境变量 (关键改进)
-    // -----------------------------------------------------------
-    // 覆盖 git.exe, code.exe, node.exe 等 CLI 工具
-    for p in get_path_env_dirs() {
-        scan_roots.insert(p);
+    fn fingerprint(&self) -> String {
+        format!("desktops={:?};locales={:?};{}", self.current_desktops, self.locales, self.plan.fingerprint)
     }
+}
 
-    // -----------------------------------------------------------
-    // C. 磁盘全盘扫描 (查漏补缺)
-    // -----------------------------------------------------------
-    #[cfg(target_os = "windows")]
-    {
-        // 扫描 D: 到 Z:
-        // 注意：全盘扫描非常慢，建议后续加上开关控制或限制深度
-        for drive_char in b'D'..=b'Z' {
-            let drive_str = format!("{}:\\", drive_char as char);
-            let drive_path = PathBuf::from(&drive_str);
-            if drive_path.exists() {
-                scan_roots.insert(drive_path);
+#[derive(Default)]
+struct DirScan {
+    items: Vec<SearchResult>,
+    records: Vec<(PathBuf, DirRecord)>,
+}
+
+impl DirScan {
+    fn merge(mut self, other: DirScan) -> DirScan {
+        self.items.extend(other.items);
+        self.records.extend(other.records);
+        self
+    }
+}
+
+fn dir_mtime(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+// 递归扫描一个目录 (depth 为相对根目录的深度，根目录为 0；parent_ignores 为上级目录的忽略规则)
+fn scan_dir(dir: &Path, depth: usize, rule: &CompiledRule, parent_ignores: &IgnoreStack, ctx: &ScanContext, old_cache: &ScanCache) -> DirScan {
+    if ctx.progress.is_cancelled() {
+        return DirScan::default();
+    }
+
+    // 子目录若是符号链接，只在第一次遇到其目标时进入
+    if depth > 0 {
+        let is_link = std::fs::symlink_metadata(dir).map(|m| m.file_type().is_symlink()).unwrap_or(false);
+        if is_link {
+            let Ok(target) = dir.canonicalize() else { return DirScan::default() };
+            if !ctx.visited_links.lock().unwrap_or_else(|e| e.into_inner()).insert(target) {
+                return DirScan::default();
             }
         }
     }
 
-    let roots_vec: Vec<PathBuf> = scan_roots.into_iter().collect();
+    let Some(mtime) = dir_mtime(dir) else { return DirScan::default() };
+    // 命令只收录根目录第一层 (PATH 不递归)
+    let with_commands = rule.commands && depth == 0;
+
+    let old = old_cache.dirs.get(dir).filter(|old| {
+        old.mtime == mtime && old.with_commands == with_commands && files_unchanged(&old.entry_files)
+    });
+
+    // 目录未变化时忽略文件的列表也不会变，只需确认其内容没有被修改
+    let ignores = if rule.ignore_files {
+        let (files, git_root) = match old {
+            Some(old) if ignore_rules::ignore_files_unchanged(&old.ignore_files) => (old.ignore_files.clone(), old.git_root),
+            _ => (ignore_rules::ignore_files_in(dir), ignore_rules::is_git_root(dir)),
+        };
+        parent_ignores.child(&files, git_root)
+    } else {
+        IgnoreStack::default()
+    };
+
+    let record = match old {
+        Some(old) if old.ignore_stamp == ignores.stamp() => old.clone(),
+        _ => read_dir_record(dir, mtime, with_commands, rule, &ignores, ctx),
+    };
+    ctx.progress.dir_visited(record.items.len());
+
+    let mut scan = DirScan {
+        items: record.items.clone(),
+        records: Vec::new(),
+    };
+    if depth + 1 < rule.max_depth {
+        let children = record.subdirs.par_iter()
+            .map(|sub| scan_dir(sub, depth + 1, rule, &ignores, ctx, old_cache))
+            .reduce(DirScan::default, DirScan::merge);
+        scan = scan.merge(children);
+    }
+    scan.records.push((dir.to_path_buf(), record));
+    scan
+}
+
+// 记录的文件是否都没有被修改过 (增删会改变目录 mtime，这里只需检查内容修改)
+fn files_unchanged(files: &[(PathBuf, SystemTime)]) -> bool {
+    files.iter().all(|(path, mtime)| std::fs::metadata(path).and_then(|m| m.modified()).ok() == Some(*mtime))
+}
+
+// 条目内容来自文件内容的入口文件：编辑后目录 mtime 不变，需要记录各自的修改时间
+fn is_entry_file(path: &Path) -> bool {
+    path.extension().and_then(OsStr::to_str)
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("desktop") || ext.eq_ignore_ascii_case("lnk"))
+}
+
+// 读取目录内容：识别可启动文件，记录需要继续扫描的子目录
+fn read_dir_record(dir: &Path, mtime: SystemTime, with_commands: bool, rule: &CompiledRule, ignores: &IgnoreStack, ctx: &ScanContext) -> DirRecord {
+    let mut record = DirRecord {
+        mtime,
+        with_commands,
+        items: Vec::new(),
+        entry_files: Vec::new(),
+        subdirs: Vec::new(),
+        ignore_files: Vec::new(),
+        git_root: false,
+        ignore_stamp: ignores.stamp(),
+    };
+    if rule.ignore_files {
+        record.ignore_files = ignore_rules::ignore_files_in(dir);
+        record.git_root = ignore_rules::is_git_root(dir);
+    }
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            ctx.progress.record_error(format!("{}: {}", dir.display(), e));
+            return record;
+        }
+    };
+
+    let mut visited = 0;
+    for entry in entries.filter_map(|e| e.ok()) {
+        visited += 1;
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        // 跳过隐藏文件
+        if name.starts_with('.') { continue; }
+
+        let path = entry.path();
+        if is_garbage_path(&path.to_string_lossy()) { continue; }
+        if rule.is_excluded(&path) { continue; }
+        if !rule.follow_symlinks && entry.file_type().map(|t| t.is_symlink()).unwrap_or(false) { continue; }
+
+        // 跟随符号链接判断真实类型
+        let Ok(meta) = std::fs::metadata(&path) else { continue };
+        if ignores.is_ignored(&path, meta.is_dir()) { continue; }
+        if meta.is_dir() {
+            // 在进入目录前就进行过滤，大幅提升性能
+            if !ctx.plan.is_blacklisted_folder(&name) {
+                record.subdirs.push(path);
+            }
+        } else if meta.is_file() {
+            if is_entry_file(&path) {
+                if let Ok(modified) = meta.modified() {
+                    record.entry_files.push((path.clone(), modified));
+                }
+            }
+            if let Some(item) = file_to_result(&path, &meta, with_commands, rule, ctx) {
+                record.items.push(item);
+            }
+        }
+    }
+    ctx.progress.files_visited(visited);
+    record
+}
+
+fn file_to_result(path: &Path, meta: &std::fs::Metadata, with_commands: bool, rule: &CompiledRule, ctx: &ScanContext) -> Option<SearchResult> {
+    if !rule.is_included(path) {
+        return None;
+    }
+    if !is_launchable(path, rule, ctx.plan) {
+        return if with_commands && is_unix_command(path, meta) {
+            Some(command_to_result(path, &ctx.plan.transliteration))
+        } else {
+            None
+        };
+    }
+
+    let ext = path.extension().and_then(OsStr::to_str).unwrap_or("").to_ascii_lowercase();
+    if ext == "desktop" {
+        return desktop_entry_to_result(path, &ctx.current_desktops, &ctx.locales, &ctx.plan.transliteration);
+    }
+    if ext == "lnk" {
+        return Some(shortcut_to_result(path, &ctx.plan.transliteration));
+    }
+
+    let name = clean_filename(path);
+    let (pinyin, variants) = ctx.plan.transliteration.readings(&name);
     
-    // -----------------------------------------------------------
-    // D. 并行扫描执行
-    // -----------------------------------------------------------
-    let scanned_results: Vec<SearchResult> = roots_vec.par_iter()
-        .flat_map(|root| {
-            // 差异化深度策略：
-            // - 用户目录（下载/文档）：杂文件多，限制浅层扫描 (Depth 5)
-            // - 根驱动器 (D:\)：防止进入深层备份目录，限制中等深度 (Depth 10-15)
-            // - 开始菜单/PATH：本身就是存放程序的，允许较深 (Depth 20+)
-            
-            let root_str = root.to_string_lossy();
-            let is_user_garbage = root_str.contains("Downloads") || root_str.contains("Documents");
-            let is_root_drive = root_str.len() <= 3 && root_str.ends_with(":\\"); // e.g. "D:\"
-
-            let max_depth = if is_user_garbage { 4 } 
-                            else if is_root_drive { 8 } 
-                            else { 30 };
-
-            WalkDir::new(root)
-                .skip_hidden(true) // 跳过隐藏文件
-                .follow_links(true) 
-                .max_depth(max_depth) 
-                .process_read_dir(|_depth, _path, _state, children| {
-                    // 在进入目录前就进行过滤，大幅提升性能
-                    children.retain(|entry_result| {
-                        match entry_result {
-                            Ok(entry) => {
-                                if entry.file_type().is_dir() {
-                                    !is_critical_garbage_folder(&entry.file_name().to_string_lossy())
-                                } else {
-                                    true 
-                                }
-                            },
-                            Err(_) => false,
-                        }
-                    });
-                })
-                .into_iter()
-                .filter_map(|e| e.ok())
-                .filter(|e| {
-                    let path = e.path();
-                    let path_str = path.to_string_lossy();
-                    
-                    if is_garbage_path(&path_str) { return false; }
-                    
-                    is_launchable(e)
-                })
-                .map(|e| {
-                    let path = e.path();
-                    let name = clean_filename(&path);
-                    let ext = path.extension().and_then(OsStr::to_str).unwrap_or("").to_ascii_lowercase();
-                    let (pinyin, abbr) = generate_pinyin_data(&name);
-                    
-                    SearchResult::new(
-                        path.to_string_lossy().to_string(),
-                        name,
-                        get_file_type_display(&ext), // Subtitle 建议显示类型或路径
-                        pinyin,
-                        abbr
-                    )
-                })
-                .collect::<Vec<_>>()
+    let mut result = SearchResult::new(
+        path.to_string_lossy().to_string(),
+        name,
+        get_file_type_display(&ext), // Subtitle 建议显示类型或路径
+        pinyin
+    );
+    result.pinyin_variants = variants;
+    Some(result)
+}
+
+// ==========================================
+// 4.1 文件监听的增量更新
+// ==========================================
+
+// 需要实时监听的来源：只监听存放程序入口的目录，下载/文档/整盘及自定义目录等仍然依赖刷新
+const WATCHED_SOURCES: &[&str] = &["@start_menu", "@desktop", "@applications", "@path"];
+
+impl ScanPlan {
+    // 需要实时监听的目录 (路径, 是否递归)
+    pub fn watch_roots(&self) -> Vec<(PathBuf, bool)> {
+        self.roots.iter()
+            .filter(|r| WATCHED_SOURCES.contains(&r.source.as_str()))
+            // PATH 目录本身就不递归
+            .map(|r| (r.path.clone(), r.source != "@path"))
+            .collect()
+    }
+
+    // 变化的路径是否落在扫描时会被跳过的位置 (隐藏文件、黑名单目录、规则排除的路径)
+    // 只检查相对 root 的部分：root 本身可能就在隐藏目录里 (~/.local/share/applications)
+    fn is_ignored_change(&self, root: &ScanRoot, path: &Path) -> bool {
+        let Ok(relative) = path.strip_prefix(&root.path) else { return true };
+        if is_garbage_path(&path.to_string_lossy()) || root.rule.is_excluded(path) {
+            return true;
+        }
+        if !root.rule.follow_symlinks && std::fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_symlink()) {
+            return true;
+        }
+        // 忽略规则逐级检查：被忽略的目录在扫描时整个被剪掉，其中的文件即使不匹配规则也不会收录
+        let mut ignores = if root.rule.ignore_files { IgnoreStack::for_dir(&root.path) } else { IgnoreStack::default() };
+        let mut current = root.path.clone();
+        let mut components = relative.components().peekable();
+        while let Some(component) = components.next() {
+            let name = component.as_os_str().to_string_lossy();
+            if name.starts_with('.') {
+                return true;
+            }
+            current.push(component);
+            let is_last = components.peek().is_none();
+            // 最后一段是文件名，黑名单只针对目录
+            if !is_last && self.is_blacklisted_folder(&name) {
+                return true;
+            }
+            if root.rule.ignore_files {
+                if ignores.is_ignored(&current, !is_last || current.is_dir()) {
+                    return true;
+                }
+                if !is_last {
+                    ignores = ignores.child(&ignore_rules::ignore_files_in(&current), ignore_rules::is_git_root(&current));
+                }
+            }
+        }
+        false
+    }
+}
+
+// 把一批变化的路径应用到已有索引：先移除这些路径 (及其子路径) 下的旧条目，
+// 再按所属规则重新识别仍然存在的文件/目录，最后整体去重。
+// 索引中是去重后的条目，合并掉的条目只剩别名：涉及变化路径的整个去重组都拆开，
+// 组内其他成员从磁盘重新识别后再参与去重 (删除 .desktop 后它合并的 PATH 命令重新出现，删除被合并的文件后别名消失)
+// 所有路径都与索引无关时返回 None
+pub fn apply_changes(plan: &ScanPlan, apps: Vec<SearchResult>, changed: &[PathBuf]) -> Option<Vec<SearchResult>> {
+    // 忽略文件本身的变化意味着整个目录需要按新规则重新识别
+    let mut changed: Vec<PathBuf> = changed.iter()
+        .map(|path| {
+            let is_ignore_file = path.file_name().and_then(OsStr::to_str)
+                .is_some_and(|name| ignore_rules::IGNORE_FILE_NAMES.contains(&name));
+            match path.parent() {
+                Some(dir) if is_ignore_file => dir.to_path_buf(),
+                _ => path.clone(),
+            }
         })
         .collect();
+    changed.sort();
+    changed.dedup();
+
+    let changed: Vec<(&PathBuf, &ScanRoot)> = changed.iter()
+        .filter_map(|path| plan.root_for(path).map(|root| (path, root)))
+        .filter(|(path, root)| !plan.is_ignored_change(root, path))
+        .collect();
+    if changed.is_empty() {
+        return None;
+    }
 
-    deduplicate(scanned_results)
+    let progress = ScanProgress::default();
+    let ctx = ScanContext::new(plan, &progress);
+    let no_cache = ScanCache::default();
+
+    let is_changed = |id: &str| changed.iter().any(|(p, _)| Path::new(id).starts_with(p));
+    let mut regroup: Vec<PathBuf> = Vec::new();
+    let mut apps: Vec<SearchResult> = apps.into_iter()
+        .filter(|a| {
+            let affected = is_changed(&a.id) || a.duplicates.iter().any(|d| is_changed(d));
+            if affected {
+                regroup.extend(std::iter::once(&a.id).chain(&a.duplicates).filter(|id| !is_changed(id)).map(PathBuf::from));
+            }
+            !affected
+        })
+        .collect();
+
+    for (path, root) in changed {
+        // 已被删除：上面已经移除，无需处理
+        let Ok(meta) = std::fs::metadata(path) else { continue };
+        let depth = path.strip_prefix(&root.path).map(|r| r.components().count()).unwrap_or(0);
+        if meta.is_dir() {
+            if depth < root.rule.max_depth {
+                let parent_ignores = match path.parent() {
+                    Some(parent) if root.rule.ignore_files => IgnoreStack::for_dir(parent),
+                    _ => IgnoreStack::default(),
+                };
+                apps.extend(scan_dir(path, depth, &root.rule, &parent_ignores, &ctx, &no_cache).items);
+            }
+        } else if meta.is_file() {
+            apps.extend(identify_file(path, &meta, root, depth, &ctx));
+        }
+    }
+    // 受影响的去重组中未变化的成员
+    for path in regroup {
+        let Some(root) = plan.root_for(&path) else { continue };
+        let Ok(meta) = std::fs::metadata(&path) else { continue };
+        let depth = path.strip_prefix(&root.path).map(|r| r.components().count()).unwrap_or(0);
+        apps.extend(identify_file(&path, &meta, root, depth, &ctx));
+    }
+
+    Some(deduplicate(apps))
+}
+
+// 按所属规则识别单个文件 (depth 为相对根目录的层数，根目录下的文件为 1)
+fn identify_file(path: &Path, meta: &std::fs::Metadata, root: &ScanRoot, depth: usize, ctx: &ScanContext) -> Option<SearchResult> {
+    if depth > root.rule.max_depth {
+        return None;
+    }
+    let with_commands = root.rule.commands && depth == 1;
+    file_to_result(path, meta, with_commands, &root.rule, ctx)
 }
 
 // ==========================================
-// 5. 启发式去重 (Heuristic Deduplication)
+// 5. 按真实身份去重 (Identity-based Deduplication)
 // ==========================================
-fn deduplicate(items: Vec<SearchResult>) -> Vec<SearchResult> {
-    let mut final_map = HashMap::new();
-    
-    for app in items {
-        // 使用文件名作为去重主键 (e.g., "Visual Studio Code")
-        let key = app.title.to_lowercase();
-        
-        final_map.entry(key)
-            .and_modify(|existing: &mut SearchResult| {
-                let new_is_shortcut = app.subtitle == "Shortcut";
-                let old_is_shortcut = existing.subtitle == "Shortcut";
-                let new_path_len = app.id.len(); 
-                let old_path_len = existing.id.len();
-
-                // 决策逻辑：保留哪一个？
-                // 1. 快捷方式优先 (.lnk) - 因为它们通常带有正确的图标和启动参数
-                // 2. 如果类型相同，保留路径更短的 - (C:\bin\app.exe 优于 C:\...build\release\app.exe)
-                // 3. 避免保留 "Uninstall" 相关的（虽然前面已过滤，这里是双保险）
-                
-                if new_is_shortcut && !old_is_shortcut {
-                    *existing = app.clone();
-                } else if new_is_shortcut == old_is_shortcut {
-                    if new_path_len < old_path_len {
-                         *existing = app.clone();
-                    }
+// 同一个程序可能以多种形式出现：开始菜单/桌面的 .lnk、exe 本身、/bin 与 /usr/bin 的符号链接、
+// 指向同一二进制的 .desktop 与 PATH 命令。按"解析后的启动目标"分组，而不是按标题：
+// - 同组只保留一条，其余条目的标题记为别名，仍然可以被搜到
+// - 不同程序即使标题相同也都保留 (副标题是各自的路径，可以区分)
+
+// 路径比较键：Windows 下统一分隔符并忽略大小写
+fn path_key(path: &str) -> String {
+    if cfg!(target_os = "windows") {
+        path.replace('/', "\\").to_lowercase()
+    } else {
+        path.to_string()
+    }
+}
+
+// 去重用的真实路径：解析包括文件本身在内的所有符号链接 (/bin/python3 -> /usr/bin/python3.12)；
+// 文件不存在 (悬空链接) 时只解析所在目录，目录也不存在则保持原样 (例如目标在另一台机器的驱动器上)。
+// 只用作比较键，条目的 action_data 仍是未解析的路径：rustup、busybox 等多合一程序靠 argv[0] 区分功能，
+// 启动时必须使用链接本身
+fn resolve_path(path: &str) -> String {
+    let path = Path::new(path);
+    let resolved = std::fs::canonicalize(path).unwrap_or_else(|_| match (path.parent(), path.file_name()) {
+        (Some(dir), Some(name)) if !dir.as_os_str().is_empty() => std::fs::canonicalize(dir)
+            .map(|d| d.join(name))
+            .unwrap_or_else(|_| path.to_path_buf()),
+        _ => path.to_path_buf(),
+    });
+    path_key(resolved.to_string_lossy().trim_start_matches(r"\\?\"))
+}
+
+// 条目的身份：最终会被执行的程序 + 参数
+// - .desktop / PATH 命令：命令行第一个参数在 PATH 中解析后的真实路径，加上其余参数
+//   (flatpak run A 与 flatpak run B 是两个程序)
+// - .lnk：解析出的目标 + 快捷方式参数
+// - 其他文件：自身的真实路径
+fn identity_key(app: &SearchResult) -> String {
+    let mut args: Vec<String> = Vec::new();
+    let program = match app.action_type.as_str() {
+        "app" | "command" => {
+            let mut argv = desktop_entry::split_exec(&app.action_data).unwrap_or_default().into_iter();
+            match argv.next() {
+                Some(program) => {
+                    args.extend(argv);
+                    desktop_entry::find_executable(&program)
+                        .map(|p| p.to_string_lossy().to_string())
+                        .unwrap_or(program)
                 }
-            })
-            .or_insert(app);
+                None => app.id.clone(),
+            }
+        }
+        _ => {
+            args.extend(app.arguments.clone());
+            app.target.clone().unwrap_or_else(|| app.id.clone())
+        }
+    };
+
+    let mut key = resolve_path(&program);
+    for arg in args {
+        key.push('\0');
+        key.push_str(&arg);
+    }
+    key
+}
+
+// 同组内的优先级 (越小越好)：
+// 1. 快捷方式 (.lnk) - 通常带有正确的图标、启动参数和友好的名称
+// 2. .desktop - 有本地化名称和图标
+// 3. 其他 (exe / 命令 / 脚本)
+fn source_rank(app: &SearchResult) -> u8 {
+    if app.file_type == "Shortcut" {
+        0
+    } else if app.id.ends_with(".desktop") {
+        1
+    } else {
+        2
+    }
+}
+
+// 把被合并掉的条目并入保留的条目：不同的标题变成别名，关键词合并，并记下它的 id
+fn absorb_duplicate(winner: &mut SearchResult, dup: SearchResult) {
+    let known = |winner: &SearchResult, text: &str| {
+        winner.title.eq_ignore_ascii_case(text) || winner.aliases.iter().any(|a| a.text.eq_ignore_ascii_case(text))
+    };
+
+    winner.duplicates.push(dup.id.clone());
+    winner.duplicates.extend(dup.duplicates);
+
+    if !known(winner, &dup.title) {
+        winner.aliases.push(Alias {
+            text: dup.title,
+            pinyin: dup.pinyin,
+            pinyin_variants: dup.pinyin_variants,
+        });
+    }
+    for alias in dup.aliases {
+        if !known(winner, &alias.text) {
+            winner.aliases.push(alias);
+        }
+    }
+    for keyword in dup.keywords {
+        if !winner.keywords.contains(&keyword) {
+            winner.keywords.push(keyword);
+        }
+    }
+}
+
+fn deduplicate(items: Vec<SearchResult>) -> Vec<SearchResult> {
+    // 解析身份需要访问文件系统 (canonicalize)，并行计算
+    let keyed: Vec<(String, SearchResult)> = items.into_par_iter()
+        .map(|app| (identity_key(&app), app))
+        .collect();
+
+    let mut groups: HashMap<String, Vec<SearchResult>> = HashMap::new();
+    for (key, app) in keyed {
+        groups.entry(key).or_default().push(app);
     }
 
-    let mut final_list: Vec<SearchResult> = final_map.into_values().collect();
+    let mut final_list: Vec<SearchResult> = groups.into_values()
+        .map(|mut group| {
+            // 优先级相同时保留路径更短的 (C:\bin\app.exe 优于 C:\...build\release\app.exe)
+            group.sort_by(|a, b| {
+                source_rank(a).cmp(&source_rank(b))
+                    .then(a.id.len().cmp(&b.id.len()))
+                    .then(a.id.cmp(&b.id))
+            });
+            let mut group = group.into_iter();
+            let mut winner = group.next().expect("group is never empty");
+            for dup in group {
+                absorb_duplicate(&mut winner, dup);
+            }
+            winner
+        })
+        .collect();
     
     // 排序优化：短的标题排前面（通常更匹配），或者按字典序
     final_list.sort_by(|a, b| {
@@ -358,4 +1030,226 @@ fn deduplicate(items: Vec<SearchResult>) -> Vec<SearchResult> {
         }
     });
     final_list
-}
\ No newline at end of file
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::models::PinyinReading;
+    use std::os::unix::fs::symlink;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("omnibox-scanner-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("usr/bin")).unwrap();
+        symlink("usr/bin", root.join("bin")).unwrap();
+        root
+    }
+
+    fn command(path: &Path) -> SearchResult {
+        let name = path.file_name().unwrap().to_string_lossy().to_string();
+        let mut item = SearchResult::new(path.to_string_lossy().to_string(), name, "Command".into(), PinyinReading::default());
+        item.action_type = "command".into();
+        item.action_data = desktop_entry::join_exec(&[item.id.clone()]);
+        item
+    }
+
+    #[test]
+    fn localized_names_are_titles_or_aliases() {
+        let root = temp_dir("localized");
+        let entry = root.join("files.desktop");
+        std::fs::write(&entry, "[Desktop Entry]\nType=Application\nName=Files\nName[zh_CN]=文件管理器\nKeywords=folder;\nKeywords[zh_CN]=文件夹;\nExec=nautilus\n").unwrap();
+        let translit = Transliteration::new(&AppSettings::default().transliteration);
+        let locales = |list: &[&str]| list.iter().map(|l| l.to_string()).collect::<Vec<_>>();
+
+        // 英文环境显示 Name，中文名作为别名，同样生成拼音 (输入 "wjgl" 能找到)
+        let item = desktop_entry_to_result(&entry, &[], &locales(&["en_US", "en"]), &translit).unwrap();
+        assert_eq!(item.title, "Files");
+        assert_eq!(item.keywords, vec!["folder", "文件夹"]);
+        assert_eq!(item.aliases.len(), 1);
+        assert_eq!(item.aliases[0].text, "文件管理器");
+        assert_eq!(item.aliases[0].pinyin.acronym, "wjglq");
+
+        let item = desktop_entry_to_result(&entry, &[], &locales(&["zh_CN", "zh"]), &translit).unwrap();
+        assert_eq!(item.title, "文件管理器");
+        assert_eq!(item.pinyin.full, "wenjianguanliqi");
+        assert_eq!(item.aliases[0].text, "Files");
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn path_commands_need_the_execute_bit_and_a_known_header() {
+        use std::os::unix::fs::PermissionsExt;
+        let root = temp_dir("commands");
+        let bin = root.join("usr/bin");
+        std::fs::create_dir_all(bin.join("libexec")).unwrap();
+        let write = |name: &str, content: &str, mode: u32| {
+            std::fs::write(bin.join(name), content).unwrap();
+            std::fs::set_permissions(bin.join(name), std::fs::Permissions::from_mode(mode)).unwrap();
+        };
+        write("git", "#!/bin/sh\n", 0o755);
+        write("notes", "#!/bin/sh\n", 0o644);
+        write("blob", "data", 0o755);
+        // 命令只收录根目录第一层
+        write("libexec/worker", "#!/bin/sh\n", 0o755);
+
+        let mut rule = ScanRule::new(&bin.to_string_lossy(), 2);
+        rule.commands = true;
+        let settings = AppSettings { scan_rules: vec![rule], ..AppSettings::default() };
+        let (apps, _, _) = scan_applications(&ScanPlan::new(&settings), &ScanCache::default(), &ScanProgress::default());
+        assert_eq!(apps.len(), 1);
+        assert_eq!(apps[0].title, "git");
+        assert_eq!(apps[0].file_type, "Command");
+        assert_eq!(apps[0].action_type, "command");
+        assert_eq!(apps[0].action_data, bin.join("git").to_string_lossy());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn scan_rules_limit_depth_extensions_and_paths() {
+        let root = temp_dir("rules");
+        let tools = root.join("tools");
+        for dir in ["bin/nested", "backup/bin", "node_modules/bin"] {
+            std::fs::create_dir_all(tools.join(dir)).unwrap();
+        }
+        for file in ["run.sh", "bin/build.sh", "bin/build.py", "bin/notes.txt", "bin/uninstall.sh", "bin/nested/deep.sh", "backup/bin/old.sh", "node_modules/bin/dep.sh"] {
+            std::fs::write(tools.join(file), "").unwrap();
+        }
+
+        let mut rule = ScanRule::new(&tools.to_string_lossy(), 2);
+        rule.include = vec!["**/bin/*".into()];
+        rule.exclude = vec!["**/backup/**".into()];
+        rule.extensions = vec![".SH".into(), "py".into()];
+        let settings = AppSettings { scan_rules: vec![rule], ..AppSettings::default() };
+        let (apps, _, _) = scan_applications(&ScanPlan::new(&settings), &ScanCache::default(), &ScanProgress::default());
+        let mut found: Vec<_> = apps.iter()
+            .map(|a| Path::new(&a.id).strip_prefix(&tools).unwrap().to_string_lossy().to_string())
+            .collect();
+        found.sort();
+        // run.sh 不匹配 include，deep.sh 超出深度，old.sh 被排除，dep.sh 在黑名单目录，uninstall 是噪音文件
+        assert_eq!(found, vec!["bin/build.py", "bin/build.sh"]);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn file_symlinks_are_resolved_for_deduplication() {
+        let root = temp_dir("dedup");
+        std::fs::write(root.join("usr/bin/python3.12"), "").unwrap();
+        symlink("python3.12", root.join("usr/bin/python3")).unwrap();
+        let real = resolve_path(&root.join("usr/bin/python3.12").to_string_lossy());
+        assert_eq!(resolve_path(&root.join("bin/python3").to_string_lossy()), real);
+
+        let link = command(&root.join("bin/python3"));
+        let items = deduplicate(vec![command(&root.join("usr/bin/python3.12")), link.clone()]);
+        assert_eq!(items.len(), 1);
+        // 保留的条目仍使用未解析的路径启动，另一个名称成为别名
+        assert_eq!(items[0].action_data, link.action_data);
+        assert_eq!(items[0].aliases.len(), 1);
+        assert_eq!(items[0].aliases[0].text, "python3.12");
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn edited_entry_files_are_reread() {
+        let root = temp_dir("entries");
+        let apps = root.join("usr/share/applications");
+        std::fs::create_dir_all(&apps).unwrap();
+        let entry = apps.join("tool.desktop");
+        let write = |content: &str, secs: u64| {
+            std::fs::write(&entry, content).unwrap();
+            let modified = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(secs);
+            std::fs::File::options().write(true).open(&entry).unwrap().set_modified(modified).unwrap();
+        };
+        let settings = AppSettings { scan_rules: vec![ScanRule::new(&apps.to_string_lossy(), 1)], ..AppSettings::default() };
+        let plan = ScanPlan::new(&settings);
+        let scan = |cache: &ScanCache| scanner_titles(scan_applications(&plan, cache, &ScanProgress::default()));
+
+        write("[Desktop Entry]\nType=Application\nName=Tool\nExec=/bin/true\nNoDisplay=true\n", 1_000_000);
+        let (titles, cache) = scan(&ScanCache::default());
+        assert!(titles.is_empty());
+        let dir_mtime = std::fs::metadata(&apps).unwrap().modified().unwrap();
+
+        // 原地编辑：目录 mtime 不变，按文件的修改时间发现变化
+        write("[Desktop Entry]\nType=Application\nName=Tool\nExec=/bin/true\n", 2_000_000);
+        assert_eq!(std::fs::metadata(&apps).unwrap().modified().unwrap(), dir_mtime);
+        let (titles, cache) = scan(&cache);
+        assert_eq!(titles, vec!["Tool"]);
+        write("[Desktop Entry]\nType=Application\nName=Renamed\nExec=/bin/true\n", 3_000_000);
+        let (titles, _) = scan(&cache);
+        assert_eq!(titles, vec!["Renamed"]);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    fn scanner_titles((apps, cache, _): (Vec<SearchResult>, ScanCache, ScanSummary)) -> (Vec<String>, ScanCache) {
+        (apps.into_iter().map(|a| a.title).collect(), cache)
+    }
+
+    // usr/share/applications/tool.desktop 与 PATH 命令 usr/bin/tool 指向同一个程序
+    fn desktop_and_command(name: &str) -> (PathBuf, ScanPlan, PathBuf, PathBuf) {
+        use std::os::unix::fs::PermissionsExt;
+        let root = temp_dir(name);
+        let apps = root.join("usr/share/applications");
+        std::fs::create_dir_all(&apps).unwrap();
+        let command = root.join("usr/bin/tool");
+        std::fs::write(&command, "#!/bin/sh\n").unwrap();
+        std::fs::set_permissions(&command, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let entry = apps.join("tool.desktop");
+        std::fs::write(&entry, format!("[Desktop Entry]\nType=Application\nName=Tool Launcher\nExec={} %U\n", command.display())).unwrap();
+
+        let mut path_rule = ScanRule::new(&root.join("usr/bin").to_string_lossy(), 1);
+        path_rule.commands = true;
+        let settings = AppSettings {
+            scan_rules: vec![ScanRule::new(&apps.to_string_lossy(), 1), path_rule],
+            ..AppSettings::default()
+        };
+        (root, ScanPlan::new(&settings), entry, command)
+    }
+
+    #[test]
+    fn watcher_restores_duplicates_of_a_deleted_winner() {
+        let (root, plan, entry, command) = desktop_and_command("winner");
+        let (apps, _, _) = scan_applications(&plan, &ScanCache::default(), &ScanProgress::default());
+        assert_eq!(apps.len(), 1);
+        assert_eq!(apps[0].id, entry.to_string_lossy());
+        assert_eq!(apps[0].duplicates, vec![command.to_string_lossy().to_string()]);
+
+        std::fs::remove_file(&entry).unwrap();
+        let apps = apply_changes(&plan, apps, std::slice::from_ref(&entry)).unwrap();
+        assert_eq!(apps.len(), 1);
+        assert_eq!(apps[0].id, command.to_string_lossy());
+        assert!(apps[0].duplicates.is_empty());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn watcher_drops_aliases_of_a_deleted_duplicate() {
+        let (root, plan, entry, command) = desktop_and_command("duplicate");
+        let (apps, _, _) = scan_applications(&plan, &ScanCache::default(), &ScanProgress::default());
+        assert_eq!(apps[0].aliases.iter().map(|a| a.text.as_str()).collect::<Vec<_>>(), vec!["tool"]);
+
+        std::fs::remove_file(&command).unwrap();
+        let apps = apply_changes(&plan, apps, std::slice::from_ref(&command)).unwrap();
+        assert_eq!(apps.len(), 1);
+        assert_eq!(apps[0].id, entry.to_string_lossy());
+        assert!(apps[0].aliases.is_empty());
+        assert!(apps[0].duplicates.is_empty());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn file_types_use_lowercase_extensions() {
+        assert_eq!(get_file_type_display("appimage"), "Application");
+        assert_eq!(get_file_type_display("exe"), "Application");
+        assert_eq!(get_file_type_display("sh"), "Script");
+        assert_eq!(get_file_type_display("txt"), "File");
+    }
+
+    #[test]
+    fn dangling_links_resolve_their_directory() {
+        let root = temp_dir("dangling");
+        symlink("missing", root.join("usr/bin/tool")).unwrap();
+        let expected = std::fs::canonicalize(root.join("usr/bin")).unwrap().join("tool");
+        assert_eq!(resolve_path(&root.join("bin/tool").to_string_lossy()), expected.to_string_lossy());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
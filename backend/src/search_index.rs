@@ -0,0 +1,295 @@
+use crate::normalize;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+
+// ==========================================
+// 搜索预筛选索引
+// ==========================================
+// 全量模糊匹配在十万级条目上会明显卡顿。建索引时为每个条目的可搜索文本
+// (标题、拼音、首字母、别名、关键词、文件名，统一归一化，见 normalize.rs) 记录：
+// - 三元组 (trigram) 倒排表：查询 >= 3 个字符时，包含查询所有三元组的条目
+// - 词前缀倒排表：查询为 1~2 个字符时，某个词以查询开头的条目
+// - 字符位图：条目中出现过的字符集合
+//
+// 倒排表命中的是"强候选" (查询作为连续子串/词前缀出现)。强候选足够多时只对它们打分，
+// 仅靠子序列才能模糊匹配上的条目分数本来就低，不会进入前列；强候选不足时退回到位图筛选，
+// 位图是所有匹配方式的超集，不会漏掉结果。
+// 调用方传入的 pinned 条目 (用过的条目) 总是参与打分，保证习惯加权不受影响：
+// 频率-时效分数只来自使用记录，没有 pin 的条目 frecency 为 0，被截掉也不会改变排序。
+// 查询中的每个词可以有多种拼法 (双拼、模糊音，见 pinyin_input.rs)，匹配任意一种即可。
+
+const KEY_TRIGRAM: u64 = 0;
+const KEY_PREFIX: u64 = 1 << 63;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct SearchIndex {
+    // 建索引时条目 id 序列的指纹，与当前条目列表不一致时索引作废
+    pub fingerprint: u64,
+    doc_count: usize,
+    postings: HashMap<u64, Vec<u32>>,
+    masks: Vec<u64>,
+}
+
+impl SearchIndex {
+    // docs 的第 i 项为第 i 个条目的所有可搜索文本
+    pub fn build<D, S>(docs: impl IntoIterator<Item = D>, fingerprint: u64) -> Self
+    where
+        D: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut postings: HashMap<u64, Vec<u32>> = HashMap::new();
+        let mut masks = Vec::new();
+        let mut keys = HashSet::new();
+
+        for (doc, fields) in docs.into_iter().enumerate() {
+            let doc = doc as u32;
+            let mut mask = 0u64;
+            keys.clear();
+            for field in fields {
+                let chars: Vec<char> = normalize::fold(field.as_ref()).chars().collect();
+                for &c in &chars {
+                    mask |= char_bit(c);
+                }
+                for w in chars.windows(3) {
+                    keys.insert(trigram_key(w[0], w[1], w[2]));
+                }
+                for word in words(&chars) {
+                    keys.insert(prefix_key(&word[..1]));
+                    if word.len() >= 2 {
+                        keys.insert(prefix_key(&word[..2]));
+                    }
+                }
+            }
+            // 条目按顺序处理，每个倒排表天然有序且不重复
+            for &key in &keys {
+                postings.entry(key).or_default().push(doc);
+            }
+            masks.push(mask);
+        }
+
+        Self {
+            fingerprint,
+            doc_count: masks.len(),
+            postings,
+            masks,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.doc_count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.doc_count == 0
+    }
+
+    // 需要打分的候选条目 (升序)。tokens 为查询中的各个词 (已小写)，每个词有一种或多种拼法，
+    // 条目需匹配每个词的任意一种拼法；min_strong 一般为最大结果数；pinned 为升序的、总是参与打分的条目；
+    // accept 为查询中的过滤条件 (ext:、in: 等)，先过滤再数强候选，过滤后不足 min_strong 个时同样退回到位图筛选
+    pub fn candidates(&self, tokens: &[Vec<String>], min_strong: usize, pinned: &[u32], accept: impl Fn(u32) -> bool) -> Vec<u32> {
+        let tokens: Vec<Vec<Vec<char>>> = tokens.iter()
+            .map(|spellings| spellings.iter().map(|s| s.chars().collect()).collect())
+            .collect();
+        if tokens.is_empty() {
+            return Vec::new();
+        }
+
+        // 各个词的强候选 (各拼法的并集) 求交集
+        let mut strong: Option<Vec<u32>> = None;
+        for spellings in &tokens {
+            let list = spellings.iter().fold(Vec::new(), |list, chars| {
+                merge_sorted(&list, &self.strong_candidates(chars).unwrap_or_default())
+            });
+            strong = Some(match strong {
+                Some(mut prev) => {
+                    prev.retain(|doc| list.binary_search(doc).is_ok());
+                    prev
+                }
+                None => list,
+            });
+        }
+        if let Some(mut strong) = strong {
+            strong.retain(|&doc| accept(doc));
+            if strong.len() >= min_strong {
+                let pinned: Vec<u32> = pinned.iter().copied().filter(|&doc| accept(doc)).collect();
+                return merge_sorted(&strong, &pinned);
+            }
+        }
+
+        // 强候选不足：按字符位图筛选 (模糊匹配要求词中的每个字符都出现过)
+        let token_masks: Vec<Vec<u64>> = tokens.iter()
+            .map(|spellings| spellings.iter().map(|chars| chars.iter().fold(0u64, |m, &c| m | char_bit(c))).collect())
+            .collect();
+        self.masks.iter().enumerate()
+            .filter(|(_, &mask)| token_masks.iter().all(|masks| masks.iter().any(|&m| m & !mask == 0)))
+            .map(|(doc, _)| doc as u32)
+            .filter(|&doc| accept(doc))
+            .collect()
+    }
+
+    fn strong_candidates(&self, chars: &[char]) -> Option<Vec<u32>> {
+        if chars.len() < 3 {
+            return Some(self.postings.get(&prefix_key(chars)).cloned().unwrap_or_default());
+        }
+
+        // 从最短的倒排表开始求交集
+        let mut lists = Vec::new();
+        for w in chars.windows(3) {
+            lists.push(self.postings.get(&trigram_key(w[0], w[1], w[2]))?);
+        }
+        lists.sort_by_key(|l| l.len());
+        let mut result = lists[0].clone();
+        for list in &lists[1..] {
+            result.retain(|doc| list.binary_search(doc).is_ok());
+            if result.is_empty() {
+                break;
+            }
+        }
+        Some(result)
+    }
+}
+
+// ==========================================
+// Top-K 选择
+// ==========================================
+// 只保留分数最高的 k 个 (分数, 文档号)，不需要收集并排序全部匹配项
+// 分数相同时文档号小的优先 (条目列表本身按标题长度排序)，与稳定排序的结果一致
+
+pub struct TopK {
+    k: usize,
+    // 堆顶是当前保留的最差候选，新候选只需与它比较
+    heap: BinaryHeap<Reverse<(i64, Reverse<u32>)>>,
+}
+
+impl TopK {
+    pub fn new(k: usize) -> Self {
+        Self { k, heap: BinaryHeap::with_capacity(k + 1) }
+    }
+
+    pub fn push(&mut self, score: i64, doc: u32) {
+        if self.k == 0 {
+            return;
+        }
+        let entry = Reverse((score, Reverse(doc)));
+        if self.heap.len() < self.k {
+            self.heap.push(entry);
+        } else if self.heap.peek().is_some_and(|worst| entry < *worst) {
+            self.heap.pop();
+            self.heap.push(entry);
+        }
+    }
+
+    // 按分数从高到低返回
+    pub fn into_sorted(self) -> Vec<(i64, u32)> {
+        self.heap.into_sorted_vec().into_iter()
+            .map(|Reverse((score, Reverse(doc)))| (score, doc))
+            .collect()
+    }
+}
+
+// 条目 id 序列的指纹 (顺序相关)
+pub fn fingerprint<'a>(ids: impl IntoIterator<Item = &'a str>) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for id in ids {
+        id.hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+fn trigram_key(a: char, b: char, c: char) -> u64 {
+    KEY_TRIGRAM | (a as u64) << 42 | (b as u64) << 21 | c as u64
+}
+
+fn prefix_key(chars: &[char]) -> u64 {
+    let a = chars.first().map_or(0, |&c| c as u64);
+    let b = chars.get(1).map_or(0, |&c| c as u64);
+    KEY_PREFIX | a << 21 | b
+}
+
+// a-z、0-9 各占一位，其余字符按码点散列到剩下的 28 位
+fn char_bit(c: char) -> u64 {
+    let bit = match c {
+        'a'..='z' => c as u32 - 'a' as u32,
+        '0'..='9' => 26 + (c as u32 - '0' as u32),
+        _ => 36 + (c as u32 % 28),
+    };
+    1 << bit
+}
+
+// 按非字母数字字符切分的词 ("visual studio code"、"7-zip" -> "7" "zip")
+fn words(chars: &[char]) -> impl Iterator<Item = &[char]> {
+    chars.split(|c| !c.is_alphanumeric()).filter(|w| !w.is_empty())
+}
+
+fn merge_sorted(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut out = Vec::with_capacity(a.len() + b.len());
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        match a[i].cmp(&b[j]) {
+            std::cmp::Ordering::Less => { out.push(a[i]); i += 1; }
+            std::cmp::Ordering::Greater => { out.push(b[j]); j += 1; }
+            std::cmp::Ordering::Equal => { out.push(a[i]); i += 1; j += 1; }
+        }
+    }
+    out.extend_from_slice(&a[i..]);
+    out.extend_from_slice(&b[j..]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(query: &str) -> Vec<Vec<String>> {
+        query.split_whitespace().map(|t| vec![t.to_string()]).collect()
+    }
+
+    #[test]
+    fn top_k_matches_a_stable_full_sort() {
+        let scores: Vec<i64> = vec![5, 9, 1, 9, 7, 5, 3, 9, 0, 7];
+        let mut expected: Vec<(i64, u32)> = scores.iter().enumerate().map(|(doc, &s)| (s, doc as u32)).collect();
+        expected.sort_by_key(|&(score, _)| Reverse(score));
+        for k in [0, 1, 4, 10, 20] {
+            let mut top = TopK::new(k);
+            for (doc, &score) in scores.iter().enumerate() {
+                top.push(score, doc as u32);
+            }
+            // 同分时文档号小的在前
+            assert_eq!(top.into_sorted(), expected[..k.min(scores.len())].to_vec());
+        }
+    }
+
+    #[test]
+    fn pinned_fuzzy_matches_survive_the_strong_cutoff() {
+        // 强候选已够数时，只能模糊匹配上的条目不再打分，但用过的 (pinned) 条目仍然保留
+        let mut docs: Vec<Vec<String>> = (0..5).map(|i| vec![format!("report {i}")]).collect();
+        docs.push(vec!["rapid export tool".into()]);
+        docs.push(vec!["rapid export util".into()]);
+        let index = SearchIndex::build(docs, 0);
+        assert_eq!(index.candidates(&tokens("report"), 5, &[], |_| true), vec![0, 1, 2, 3, 4]);
+        assert_eq!(index.candidates(&tokens("report"), 5, &[6], |_| true), vec![0, 1, 2, 3, 4, 6]);
+        // 强候选不足时两者都会经过位图筛选
+        assert_eq!(index.candidates(&tokens("report"), 6, &[], |_| true), (0..7).collect::<Vec<u32>>());
+    }
+
+    #[test]
+    fn path_components_are_indexed() {
+        let index = SearchIndex::build(vec![vec!["deploy.sh", "/home/u/work/deploy.sh"], vec!["other", "/x/other"]], 0);
+        assert_eq!(index.candidates(&tokens("deploy work"), 1, &[], |_| true), vec![0]);
+    }
+
+    #[test]
+    fn filters_apply_before_counting_strong_candidates() {
+        // 10 个 "report" 中只有最后一个通过过滤：过滤后强候选不足，也不能被其他强候选挤掉
+        let docs: Vec<Vec<String>> = (0..10).map(|i| vec![format!("report {i}")]).collect();
+        let index = SearchIndex::build(docs, 0);
+        assert_eq!(index.candidates(&tokens("report"), 5, &[], |_| true).len(), 10);
+        assert_eq!(index.candidates(&tokens("report"), 5, &[], |doc| doc == 9), vec![9]);
+        assert_eq!(index.candidates(&tokens("report"), 1, &[0, 9], |doc| doc >= 8), vec![8, 9]);
+        // 位图筛选同样只返回通过过滤的条目
+        assert_eq!(index.candidates(&tokens("rpt"), 5, &[], |doc| doc % 2 == 0), vec![0, 2, 4, 6, 8]);
+    }
+}
@@ -0,0 +1,309 @@
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+// ==========================================
+// Windows .lnk 快捷方式解析
+// 规范: [MS-SHLLINK] https://learn.microsoft.com/openspecs/windows_protocols/ms-shllink
+// ==========================================
+// 纯 Rust 实现，不依赖 Windows API，任何平台都可以解析 (样例文件见 tests/fixtures/shortcut)。
+// 只读取需要的部分，所有偏移都检查边界：损坏的文件返回 None 或缺少部分字段，不会 panic
+//
+// 没有使用原先声明的 lnk crate：
+// - lnk 0.3 遇到损坏的文件会 panic (assert 与越界切片，broken_link_info.lnk 即可触发)。
+//   catch_unwind 只在 panic = "unwind" 时有效，且 panic 信息仍由全局 hook 打印；
+//   要屏蔽只能替换进程全局的 panic hook，会与 rayon 的扫描线程互相干扰
+// - 它不公开 LinkInfo (LocalBasePath 是最常用的目标路径)，这部分本来就需要按规范自行读取
+
+#[derive(Debug, Clone, Default)]
+pub struct Shortcut {
+    pub target: Option<String>,
+    pub arguments: Option<String>,
+    pub working_dir: Option<String>,
+    pub icon_location: Option<String>,
+    pub description: Option<String>,
+}
+
+const HEADER_SIZE: usize = 0x4C;
+const LINK_CLSID: [u8; 16] = [0x01, 0x14, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0xC0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x46];
+
+// LinkFlags
+const HAS_LINK_TARGET_ID_LIST: u32 = 0x01;
+const HAS_LINK_INFO: u32 = 0x02;
+const HAS_NAME: u32 = 0x04;
+const HAS_RELATIVE_PATH: u32 = 0x08;
+const HAS_WORKING_DIR: u32 = 0x10;
+const HAS_ARGUMENTS: u32 = 0x20;
+const HAS_ICON_LOCATION: u32 = 0x40;
+const IS_UNICODE: u32 = 0x80;
+
+// LinkInfoFlags
+const VOLUME_ID_AND_LOCAL_BASE_PATH: u32 = 0x01;
+
+impl Shortcut {
+    pub fn parse_file(path: &Path) -> Option<Self> {
+        let data = fs::read(path).ok()?;
+        Self::parse(&data, path.parent().unwrap_or(Path::new("")))
+    }
+
+    // 不是快捷方式 (文件头不对) 时返回 None；base_dir 为 .lnk 所在目录，用于解析 RelativePath
+    fn parse(data: &[u8], base_dir: &Path) -> Option<Self> {
+        if read_u32(data, 0)? as usize != HEADER_SIZE || data.get(4..20)? != LINK_CLSID {
+            return None;
+        }
+        let link_flags = read_u32(data, 0x14)?;
+
+        let mut cursor = HEADER_SIZE;
+        if link_flags & HAS_LINK_TARGET_ID_LIST != 0 {
+            cursor += 2 + read_u16(data, cursor).unwrap_or(0) as usize;
+        }
+        let mut local_base_path = None;
+        if link_flags & HAS_LINK_INFO != 0 {
+            let info = data.get(cursor..).unwrap_or_default();
+            local_base_path = read_local_base_path(info);
+            cursor += read_u32(info, 0).unwrap_or(0) as usize;
+        }
+
+        // StringData：按固定顺序出现，是否存在由各自的标志决定；读到损坏的部分就停止，保留前面的字段
+        let unicode = link_flags & IS_UNICODE != 0;
+        let mut strings: [Option<String>; 5] = Default::default();
+        for (slot, flag) in strings.iter_mut().zip([HAS_NAME, HAS_RELATIVE_PATH, HAS_WORKING_DIR, HAS_ARGUMENTS, HAS_ICON_LOCATION]) {
+            if link_flags & flag == 0 {
+                continue;
+            }
+            match read_string_data(data, cursor, unicode) {
+                Some((s, len)) => {
+                    *slot = Some(s);
+                    cursor += len;
+                }
+                None => break,
+            }
+        }
+        let non_empty = |s: &Option<String>| s.as_deref().map(str::trim).filter(|s| !s.is_empty()).map(expand_env_vars);
+        let [name, relative_path, working_dir, arguments, icon_location] = &strings;
+
+        // 目标路径：优先 LinkInfo 中的绝对路径，其次相对 .lnk 所在目录的 RelativePath (如 ..\bin\app.exe)
+        let target = local_base_path
+            .map(|p| expand_env_vars(&p))
+            .or_else(|| {
+                non_empty(relative_path).map(|rel| {
+                    // RelativePath 使用 Windows 分隔符，按部分拼接，其他平台上同样能解析
+                    let joined = rel.split(['\\', '/'])
+                        .filter(|part| !part.is_empty())
+                        .fold(base_dir.to_path_buf(), |dir, part| dir.join(part));
+                    normalize_path(&joined).to_string_lossy().to_string()
+                })
+            });
+
+        Some(Shortcut {
+            target,
+            arguments: non_empty(arguments),
+            working_dir: non_empty(working_dir),
+            icon_location: non_empty(icon_location),
+            description: non_empty(name),
+        })
+    }
+}
+
+// ==========================================
+// LinkInfo.LocalBasePath
+// ==========================================
+
+// info 从 LinkInfo 结构的开头开始，其中的偏移都相对于这里
+fn read_local_base_path(info: &[u8]) -> Option<String> {
+    let header_size = read_u32(info, 4)? as usize;
+    let info_flags = read_u32(info, 8)?;
+    if info_flags & VOLUME_ID_AND_LOCAL_BASE_PATH == 0 {
+        return None;
+    }
+
+    let suffix_offset = read_u32(info, 24)? as usize;
+
+    // 优先使用 Unicode 版本 (LinkInfoHeaderSize >= 0x24 时才存在)
+    let unicode = if header_size >= 0x24 {
+        let base_offset = read_u32(info, 28)? as usize;
+        let suffix_offset = read_u32(info, 32)? as usize;
+        (base_offset != 0).then(|| {
+            let base = read_utf16z(info, base_offset)?;
+            let suffix = if suffix_offset != 0 { read_utf16z(info, suffix_offset)? } else { String::new() };
+            Some(base + &suffix)
+        }).flatten()
+    } else {
+        None
+    };
+
+    let path = match unicode {
+        Some(p) => p,
+        None => {
+            let base_offset = read_u32(info, 16)? as usize;
+            read_ansiz(info, base_offset)? + &read_ansiz(info, suffix_offset).unwrap_or_default()
+        }
+    };
+    if path.is_empty() { None } else { Some(path) }
+}
+
+// StringData 中的一个字符串：2 字节的字符数 + 字符 (Unicode 时为 UTF-16)，返回 (字符串, 占用的字节数)
+fn read_string_data(data: &[u8], offset: usize, unicode: bool) -> Option<(String, usize)> {
+    let count = read_u16(data, offset)? as usize;
+    let start = offset + 2;
+    if unicode {
+        let bytes = data.get(start..start + count * 2)?;
+        let units: Vec<u16> = bytes.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect();
+        Some((String::from_utf16_lossy(&units), 2 + count * 2))
+    } else {
+        let bytes = data.get(start..start + count)?;
+        Some((String::from_utf8_lossy(bytes).to_string(), 2 + count))
+    }
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    let bytes = data.get(offset..offset + 2)?;
+    Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset + 4)?;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+// 系统代码页字符串：按 lossy UTF-8 读取，纯 ASCII 路径不受影响
+fn read_ansiz(data: &[u8], offset: usize) -> Option<String> {
+    let bytes = data.get(offset..)?;
+    let end = bytes.iter().position(|&b| b == 0)?;
+    Some(String::from_utf8_lossy(&bytes[..end]).to_string())
+}
+
+fn read_utf16z(data: &[u8], offset: usize) -> Option<String> {
+    let units: Vec<u16> = data.get(offset..)?
+        .chunks_exact(2)
+        .map(|c| u16::from_le_bytes([c[0], c[1]]))
+        .take_while(|&u| u != 0)
+        .collect();
+    Some(String::from_utf16_lossy(&units))
+}
+
+// ==========================================
+// 路径辅助
+// ==========================================
+
+// 展开 Windows 风格的 %VAR% (如 %ProgramFiles%)，未定义的变量原样保留
+pub fn expand_env_vars(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    let mut rest = input;
+    while let Some(start) = rest.find('%') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        match after.find('%') {
+            Some(end) if end > 0 => {
+                let name = &after[..end];
+                match std::env::var(name) {
+                    Ok(value) => out.push_str(&value),
+                    Err(_) => {
+                        out.push('%');
+                        out.push_str(name);
+                        out.push('%');
+                    }
+                }
+                rest = &after[end + 1..];
+            }
+            _ => {
+                out.push('%');
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+// 词法上消除 "." 和 ".."，不访问文件系统 (目标可能在另一个平台/驱动器上)
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => { out.pop(); }
+            other => out.push(other.as_os_str()),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UNICODE_LINK_INFO: &[u8] = include_bytes!("../tests/fixtures/shortcut/unicode_link_info.lnk");
+    const ANSI_LINK_INFO: &[u8] = include_bytes!("../tests/fixtures/shortcut/ansi_link_info.lnk");
+    const RELATIVE_PATH: &[u8] = include_bytes!("../tests/fixtures/shortcut/relative_path.lnk");
+    const BROKEN_LINK_INFO: &[u8] = include_bytes!("../tests/fixtures/shortcut/broken_link_info.lnk");
+    const NOT_A_LINK: &[u8] = include_bytes!("../tests/fixtures/shortcut/not_a_link.lnk");
+
+    fn parse(data: &[u8]) -> Option<Shortcut> {
+        Shortcut::parse(data, Path::new("/links/start"))
+    }
+
+    #[test]
+    fn unicode_local_base_path_after_id_list() {
+        let link = parse(UNICODE_LINK_INFO).unwrap();
+        assert_eq!(link.target.as_deref(), Some("C:\\Programme\\Werkzeug\\Töol.exe"));
+        assert_eq!(link.description.as_deref(), Some("Werkzeug für Töne"));
+        assert_eq!(link.working_dir.as_deref(), Some("C:\\Programme\\Werkzeug"));
+        assert_eq!(link.arguments.as_deref(), Some("--profile \"Mein Profil\""));
+        assert_eq!(link.icon_location.as_deref(), Some("C:\\Programme\\Werkzeug\\Töol.exe"));
+    }
+
+    #[test]
+    fn ansi_local_base_path_and_strings() {
+        let link = parse(ANSI_LINK_INFO).unwrap();
+        assert_eq!(link.target.as_deref(), Some("C:\\Windows\\notepad.exe"));
+        assert_eq!(link.arguments.as_deref(), Some("/A readme.txt"));
+        assert_eq!(link.description, None);
+    }
+
+    #[test]
+    fn read_local_base_path_uses_link_info_offsets() {
+        // LinkInfo 紧跟在 0x4C 字节的文件头之后 (没有 IDList)
+        let info = &ANSI_LINK_INFO[HEADER_SIZE..];
+        assert_eq!(read_local_base_path(info).as_deref(), Some("C:\\Windows\\notepad.exe"));
+        // 有 IDList 时要先跳过它：2 字节长度 + 内容
+        let id_list = 2 + read_u16(UNICODE_LINK_INFO, HEADER_SIZE).unwrap() as usize;
+        let info = &UNICODE_LINK_INFO[HEADER_SIZE + id_list..];
+        assert_eq!(read_local_base_path(info).as_deref(), Some("C:\\Programme\\Werkzeug\\Töol.exe"));
+        assert_eq!(read_local_base_path(&UNICODE_LINK_INFO[HEADER_SIZE..]), None);
+    }
+
+    #[test]
+    fn relative_path_is_resolved_against_link_dir() {
+        let link = parse(RELATIVE_PATH).unwrap();
+        let expected = Path::new("/links").join("bin").join("app.exe");
+        assert_eq!(link.target.as_deref(), Some(expected.to_string_lossy().as_ref()));
+    }
+
+    #[test]
+    fn broken_link_info_falls_back_to_relative_path() {
+        let link = parse(BROKEN_LINK_INFO).unwrap();
+        let expected = Path::new("/links/start").join("app.exe");
+        assert_eq!(link.target.as_deref(), Some(expected.to_string_lossy().as_ref()));
+    }
+
+    #[test]
+    fn invalid_files_are_rejected_without_panicking() {
+        assert!(parse(NOT_A_LINK).is_none());
+        assert!(parse(&[]).is_none());
+        // 任意截断都只会丢失字段
+        for fixture in [UNICODE_LINK_INFO, ANSI_LINK_INFO, RELATIVE_PATH, BROKEN_LINK_INFO] {
+            for len in 0..fixture.len() {
+                let _ = parse(&fixture[..len]);
+            }
+        }
+        assert!(Shortcut::parse_file(Path::new("/nonexistent/missing.lnk")).is_none());
+    }
+
+    #[test]
+    fn expand_env_vars_keeps_unknown_and_stray_percent() {
+        std::env::set_var("OMNIBOX_SHORTCUT_TEST", "D:\\Tools");
+        assert_eq!(expand_env_vars("%OMNIBOX_SHORTCUT_TEST%\\app.exe"), "D:\\Tools\\app.exe");
+        assert_eq!(expand_env_vars("%OMNIBOX_SHORTCUT_UNSET%\\x"), "%OMNIBOX_SHORTCUT_UNSET%\\x");
+        assert_eq!(expand_env_vars("100% done %%"), "100% done %%");
+    }
+}
@@ -0,0 +1,398 @@
+use crate::models::{AppSettings, SearchResult, UsageStats, UserHabits};
+use crate::search_index::{self, SearchIndex};
+use crate::storage::Storage;
+use arc_swap::ArcSwap;
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex, MutexGuard, RwLock};
+
+// ==========================================
+// 应用状态
+// ==========================================
+// 搜索是最频繁的操作，必须不受扫描和磁盘读写影响：
+// - 条目列表与搜索索引组成不可变的快照 (IndexSnapshot)，读取时只克隆一个 Arc。
+//   扫描 / 文件监听在锁外构建好新快照后整体原子替换，正在进行的搜索继续使用旧快照
+// - 使用统计、习惯与设置 (UserData) 同样以快照形式读取。修改时在写锁下复制一份、
+//   修改后替换 (数据量很小)，写盘直接使用替换后的快照，搜索从不等待写入
+
+pub struct IndexSnapshot {
+    pub apps: Vec<SearchResult>,
+    pub index: SearchIndex, // 与 apps 一一对应 (文档号 = apps 下标)
+    // 用过的条目 (升序)，总是参与打分；执行新条目时追加
+    pinned: RwLock<Vec<u32>>,
+}
+
+impl IndexSnapshot {
+    fn new(apps: Vec<SearchResult>, index: SearchIndex, usage: &UsageStats) -> Self {
+        let mut snapshot = Self { apps, index, pinned: RwLock::new(Vec::new()) };
+        snapshot.sync_usage(usage);
+        snapshot
+    }
+
+    // 把使用统计写入条目并重新生成 pinned 列表
+    // 有使用记录的条目全部 pin，候选截断 (search_index.rs) 因此不会漏掉任何 frecency > 0 的条目
+    fn sync_usage(&mut self, usage: &UsageStats) {
+        let mut pinned = Vec::new();
+        for (doc, app) in self.apps.iter_mut().enumerate() {
+            let stat = usage.get(&app.id);
+            app.use_count = stat.use_count;
+            app.last_used = stat.last_used;
+            if stat.use_count > 0 {
+                pinned.push(doc as u32);
+            }
+        }
+        *self.pinned.get_mut().unwrap_or_else(|e| e.into_inner()) = pinned;
+    }
+
+    pub fn pinned(&self) -> Vec<u32> {
+        self.pinned.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    fn pin(&self, doc: u32) {
+        let mut pinned = self.pinned.write().unwrap_or_else(|e| e.into_inner());
+        if let Err(pos) = pinned.binary_search(&doc) {
+            pinned.insert(pos, doc);
+        }
+    }
+
+    fn position(&self, id: &str) -> Option<usize> {
+        self.apps.iter().position(|a| a.id == id)
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct UserData {
+    pub usage: UsageStats,
+    pub habits: UserHabits,
+    pub settings: AppSettings,
+    // 每次 record_use 加一，用于判断构建快照期间使用统计是否变化
+    generation: u64,
+}
+
+pub struct AppState {
+    snapshot: ArcSwap<IndexSnapshot>,
+    user: ArcSwap<UserData>,
+    // 串行化快照的更新 (全量扫描与文件监听)，避免两次更新互相覆盖
+    index_writer: Mutex<()>,
+    // 串行化 UserData 的修改
+    user_writer: Mutex<()>,
+    // 串行化 UserData 的写盘，保证后写入的总是较新的数据
+    user_saver: Mutex<()>,
+    storage: Storage,
+}
+
+impl AppState {
+    pub fn load(storage: Storage) -> Self {
+        let apps = storage.load_apps();
+        // 旧版本的设置是 bincode 格式的 settings.bin，新增字段后就无法读取；迁移到 JSON (旧文件保留不动)
+        let settings = storage.load_settings().unwrap_or_else(|| {
+            let settings = AppSettings::from(storage.load_legacy_settings());
+            storage.save_settings(&settings);
+            settings
+        });
+
+        // 旧版本把使用次数保存在条目缓存 apps_cache_v2.bin 中，使用统计文件不存在时从那里迁移 (旧文件保留不动)
+        let mut usage = storage.load_usage();
+        if usage.is_empty() {
+            usage = UsageStats::from(storage.load_legacy_apps());
+            if !usage.is_empty() {
+                storage.save_usage(&usage);
+            }
+        }
+
+        // 旧版本的习惯记录是 搜索词 -> 条目 的平铺表，迁移到前缀树 (旧文件保留不动)
+        let mut habits = storage.load_habits();
+        if habits.is_empty() {
+            habits = UserHabits::from(storage.load_legacy_habits());
+            if !habits.is_empty() {
+                storage.save_habits(&habits);
+            }
+        }
+
+        // 索引文件与 apps_cache 不对应 (如旧版本没有索引文件) 时重新建立
+        let mut index = storage.load_index();
+        if index.fingerprint != apps_fingerprint(&apps) || index.len() != apps.len() {
+            index = build_index(&apps);
+            storage.save_index(&index);
+        }
+
+        let snapshot = IndexSnapshot::new(apps, index, &usage);
+        Self {
+            snapshot: ArcSwap::from_pointee(snapshot),
+            user: ArcSwap::from_pointee(UserData { usage, habits, settings, generation: 0 }),
+            index_writer: Mutex::new(()),
+            user_writer: Mutex::new(()),
+            user_saver: Mutex::new(()),
+            storage,
+        }
+    }
+
+    // 当前快照 (不持有任何锁)
+    pub fn snapshot(&self) -> Arc<IndexSnapshot> {
+        self.snapshot.load_full()
+    }
+
+    // 当前的使用统计、习惯与设置 (不持有任何锁)
+    pub fn user(&self) -> Arc<UserData> {
+        self.user.load_full()
+    }
+
+    // 在 user_writer 下复制、修改并替换 UserData
+    fn update_user<R>(&self, f: impl FnOnce(&mut UserData) -> R) -> R {
+        let _writer = lock(&self.user_writer);
+        let mut user = UserData::clone(&self.user.load());
+        let result = f(&mut user);
+        self.user.store(Arc::new(user));
+        result
+    }
+
+    pub fn settings(&self) -> AppSettings {
+        self.user.load().settings.clone()
+    }
+
+    pub fn storage(&self) -> &Storage {
+        &self.storage
+    }
+
+    // 保存设置，返回是否需要重新扫描
+    pub fn set_settings(&self, settings: AppSettings) -> bool {
+        let rescan = self.update_user(|user| {
+            let rescan = user.settings.scan_config_differs(&settings);
+            user.settings = settings;
+            rescan
+        });
+        let _saver = lock(&self.user_saver);
+        self.storage.save_settings(&self.user().settings);
+        rescan
+    }
+
+    // 记录一次执行 (内存中)，返回条目的 (action_type, action_data)；写盘见 save_usage
+    pub fn record_use(&self, id: &str, query: &str) -> Option<(String, String)> {
+        // 在锁外查找条目 (百万级条目时需要几毫秒)
+        let mut snapshot = self.snapshot();
+        let mut doc = snapshot.position(id);
+
+        // 在 user_writer 下确认当前快照：新快照在同一把锁下确认使用统计是最新的之后才替换，
+        // 因此它要么已包含这次执行，要么已经替换完成、需要在新快照中置顶
+        let current = self.update_user(|user| {
+            user.usage.record(id);
+            user.generation += 1;
+            if !query.trim().is_empty() {
+                user.habits.record(query, id);
+            }
+            self.snapshot()
+        });
+        if !Arc::ptr_eq(&snapshot, &current) {
+            doc = current.position(id);
+            snapshot = current;
+        }
+
+        let doc = doc?;
+        snapshot.pin(doc as u32);
+        let item = &snapshot.apps[doc];
+        Some((item.action_type.clone(), item.action_data.clone()))
+    }
+
+    // 把使用统计与习惯写盘
+    pub fn save_usage(&self) {
+        let _saver = lock(&self.user_saver);
+        let user = self.user();
+        self.storage.save_usage(&user.usage);
+        self.storage.save_habits(&user.habits);
+    }
+
+    // 用全量扫描的结果替换条目列表
+    pub fn replace_apps(&self, apps: Vec<SearchResult>) -> usize {
+        let _writer = lock(&self.index_writer);
+        self.publish(apps)
+    }
+
+    // 基于当前条目列表计算新列表 (如应用文件变化)，f 返回 None 表示无需更新。
+    // 返回 (更新前, 更新后) 的条目数
+    pub fn update_apps(
+        &self,
+        f: impl FnOnce(&[SearchResult]) -> Option<Vec<SearchResult>>,
+    ) -> Option<(usize, usize)> {
+        let _writer = lock(&self.index_writer);
+        let current = self.snapshot();
+        let updated = f(&current.apps)?;
+        Some((current.apps.len(), self.publish(updated)))
+    }
+
+    // 建立索引并替换快照 (调用方持有 index_writer)，随后把条目列表和索引写盘
+    fn publish(&self, apps: Vec<SearchResult>) -> usize {
+        let index = build_index(&apps);
+        let user = self.user();
+        let mut snapshot = IndexSnapshot::new(apps, index, &user.usage);
+
+        let snapshot = {
+            // 持有 user_writer 时 record_use 无法修改使用统计；构建期间有新的执行时重新同步 (很少发生)
+            let _writer = lock(&self.user_writer);
+            let latest = self.user();
+            if latest.generation != user.generation {
+                snapshot.sync_usage(&latest.usage);
+            }
+            let snapshot = Arc::new(snapshot);
+            self.snapshot.store(snapshot.clone());
+            snapshot
+        };
+        self.storage.save_apps(&snapshot.apps);
+        self.storage.save_index(&snapshot.index);
+
+        // 删除指向已不在索引中的条目的习惯记录
+        if !self.user().habits.is_empty() {
+            let ids: HashSet<&str> = snapshot.apps.iter().map(|a| a.id.as_str()).collect();
+            if self.update_user(|user| user.habits.prune(|id| ids.contains(id))) {
+                let _saver = lock(&self.user_saver);
+                self.storage.save_habits(&self.user().habits);
+            }
+        }
+        snapshot.apps.len()
+    }
+}
+
+fn lock(mutex: &Mutex<()>) -> MutexGuard<'_, ()> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn apps_fingerprint(apps: &[SearchResult]) -> u64 {
+    search_index::fingerprint(apps.iter().map(|a| a.id.as_str()))
+}
+
+// 除打分用到的文本外，路径也建立索引，只能靠目录名匹配的条目同样成为候选
+fn build_index(apps: &[SearchResult]) -> SearchIndex {
+    let docs = apps.iter().map(|a| {
+        let mut fields: Vec<Cow<str>> = a.search_fields().into_iter().map(Cow::Borrowed).collect();
+        fields.push(a.target_path());
+        fields
+    });
+    SearchIndex::build(docs, apps_fingerprint(apps))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{DateTime, Utc};
+    use serde::Serialize;
+
+    // 基线版本的 SearchResult，按当时的字段顺序写出 apps_cache_v2.bin
+    #[derive(Serialize)]
+    struct BaselineSearchResult {
+        id: String,
+        title: String,
+        subtitle: String,
+        title_pinyin: String,
+        title_acronym: String,
+        #[serde(skip)]
+        #[allow(dead_code)]
+        score: i64,
+        action_type: String,
+        action_data: String,
+        use_count: u32,
+        last_used: Option<DateTime<Utc>>,
+        file_type: String,
+    }
+
+    fn baseline(id: &str, use_count: u32, last_used: Option<DateTime<Utc>>) -> BaselineSearchResult {
+        BaselineSearchResult {
+            id: id.into(),
+            title: "微信".into(),
+            subtitle: id.into(),
+            title_pinyin: "weixin".into(),
+            title_acronym: "wx".into(),
+            score: 0,
+            action_type: "file".into(),
+            action_data: id.into(),
+            use_count,
+            last_used,
+            file_type: "Application".into(),
+        }
+    }
+
+    fn temp_storage(name: &str) -> (std::path::PathBuf, Storage) {
+        let dir = std::env::temp_dir().join(format!("omnibox-state-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        (dir.clone(), Storage::with_dir(dir))
+    }
+
+    #[test]
+    fn usage_is_migrated_from_the_baseline_apps_cache() {
+        let (dir, storage) = temp_storage("legacy-usage");
+        let last_used = "2024-05-01T08:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let legacy = vec![baseline("C:\\WeChat.exe", 3, Some(last_used)), baseline("C:\\Other.exe", 0, None)];
+        let bytes = bincode::serialize(&legacy).unwrap();
+        std::fs::write(dir.join("apps_cache_v2.bin"), &bytes).unwrap();
+
+        let state = AppState::load(storage);
+        let user = state.user();
+        let stat = user.usage.get("C:\\WeChat.exe");
+        assert_eq!(stat.use_count, 3);
+        assert_eq!(stat.last_used, Some(last_used));
+        assert_eq!(stat.recent_visits, vec![last_used]);
+        assert_eq!(user.usage.by_id.len(), 1);
+        // 迁移结果单独保存，旧文件保留不动
+        assert_eq!(Storage::with_dir(dir.clone()).load_usage().get("C:\\WeChat.exe").use_count, 3);
+        assert_eq!(std::fs::read(dir.join("apps_cache_v2.bin")).unwrap(), bytes);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn existing_usage_is_not_overwritten() {
+        let (dir, storage) = temp_storage("existing-usage");
+        let mut usage = UsageStats::default();
+        usage.record("C:\\Other.exe");
+        storage.save_usage(&usage);
+        std::fs::write(dir.join("apps_cache_v2.bin"), bincode::serialize(&vec![baseline("C:\\WeChat.exe", 3, None)]).unwrap()).unwrap();
+
+        let user = AppState::load(storage).user();
+        assert_eq!(user.usage.get("C:\\WeChat.exe").use_count, 0);
+        assert_eq!(user.usage.get("C:\\Other.exe").use_count, 1);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    fn app(id: &str) -> SearchResult {
+        let mut item = SearchResult::new(id.into(), id.into(), "Application".into(), Default::default());
+        item.action_type = "app".into();
+        item
+    }
+
+    #[test]
+    fn searches_keep_their_snapshot_while_apps_are_replaced() {
+        let (dir, storage) = temp_storage("snapshot");
+        let state = AppState::load(storage);
+        state.replace_apps(vec![app("/a"), app("/b")]);
+        let before = state.snapshot();
+
+        assert_eq!(state.update_apps(|apps| Some(apps[..1].to_vec())), Some((2, 1)));
+        assert_eq!(before.apps.len(), 2);
+        assert_eq!(state.snapshot().apps.len(), 1);
+        // 条目列表与索引同时写盘，重新加载后一致
+        let reloaded = AppState::load(Storage::with_dir(dir.clone())).snapshot();
+        assert_eq!(reloaded.apps.len(), 1);
+        assert_eq!(reloaded.index.len(), 1);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn used_items_stay_pinned_across_new_snapshots() {
+        let (dir, storage) = temp_storage("pinned");
+        let state = AppState::load(storage);
+        state.replace_apps(vec![app("/a"), app("/b")]);
+
+        assert_eq!(state.record_use("/b", "b"), Some(("app".to_string(), "/b".to_string())));
+        assert_eq!(state.record_use("/missing", ""), None);
+        assert_eq!(state.snapshot().pinned(), vec![1]);
+
+        // 新快照按使用统计重新生成 pinned 与 use_count
+        state.replace_apps(vec![app("/b"), app("/c")]);
+        let snapshot = state.snapshot();
+        assert_eq!(snapshot.pinned(), vec![0]);
+        assert_eq!(snapshot.apps[0].use_count, 1);
+
+        state.save_usage();
+        let reloaded = AppState::load(Storage::with_dir(dir.clone()));
+        assert_eq!(reloaded.user().usage.get("/b").use_count, 1);
+        assert_eq!(reloaded.snapshot().pinned(), vec![0]);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
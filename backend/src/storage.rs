@@ -1,8 +1,19 @@
-use crate::models::{SearchResult, UserHabits, AppSettings};
+use crate::models::{SearchResult, LegacySearchResult, ScanCache, UsageStats, UserHabits, LegacyUserHabits, AppSettings, LegacyAppSettings};
+use crate::search_index::SearchIndex;
 use std::fs;
 use std::path::PathBuf;
 use tauri::api::path::cache_dir;
 
+// 条目缓存 (apps_cache / search_index / scan_cache) 的格式版本，写在文件名中。
+// bincode 不保存字段名，SearchResult 等结构的字段变化后旧文件可能报错，也可能读出错位的数据，
+// 所以每次改动这些结构 (包括 PinyinReading、DirRecord 等内部字段) 都要加一，旧版本的文件直接忽略
+const CACHE_VERSION: u32 = 11;
+
+fn cache_file(name: &str) -> String {
+    format!("{}_v{}.bin", name, CACHE_VERSION)
+}
+
+#[derive(Clone)]
 pub struct Storage {
     cache_dir: PathBuf,
 }
@@ -11,6 +22,10 @@ impl Storage {
     pub fn new() -> Self {
         let mut path = cache_dir().unwrap_or(PathBuf::from("."));
         path.push("omnibox");
+        Self::with_dir(path)
+    }
+
+    pub fn with_dir(path: PathBuf) -> Self {
         if let Err(e) = fs::create_dir_all(&path) {
             eprintln!("Error creating cache directory: {}", e);
         }
@@ -44,12 +59,51 @@ impl Storage {
         }
     }
 
-    pub fn save_apps(&self, apps: &[SearchResult]) { self.save("apps_cache_v2.bin", apps); }
-    pub fn load_apps(&self) -> Vec<SearchResult> { self.load("apps_cache_v2.bin") }
+    // 设置等需要跨版本保留的数据保存为 JSON，按字段名读取
+    fn save_json<T: serde::Serialize>(&self, filename: &str, data: &T) {
+        let path = self.get_path(filename);
+        match serde_json::to_vec_pretty(data) {
+            Ok(bytes) => {
+                if let Err(e) = fs::write(&path, bytes) {
+                    eprintln!("Failed to write {}: {}", filename, e);
+                }
+            }
+            Err(e) => eprintln!("Failed to serialize {}: {}", filename, e),
+        }
+    }
+
+    // 文件不存在时返回 None
+    fn load_json<T: serde::de::DeserializeOwned + Default>(&self, filename: &str) -> Option<T> {
+        let path = self.get_path(filename);
+        let bytes = fs::read(&path).ok()?;
+        Some(serde_json::from_slice(&bytes).unwrap_or_else(|e| {
+            eprintln!("Failed to deserialize {}: {}", filename, e);
+            T::default()
+        }))
+    }
+
+    pub fn save_apps(&self, apps: &[SearchResult]) { self.save(&cache_file("apps_cache"), apps); }
+    pub fn load_apps(&self) -> Vec<SearchResult> { self.load(&cache_file("apps_cache")) }
+
+    // 搜索索引与 apps_cache 配套保存，加载后需用 fingerprint 校验是否仍然对应
+    pub fn save_index(&self, index: &SearchIndex) { self.save(&cache_file("search_index"), index); }
+    pub fn load_index(&self) -> SearchIndex { self.load(&cache_file("search_index")) }
+
+    pub fn save_scan_cache(&self, cache: &ScanCache) { self.save(&cache_file("scan_cache"), cache); }
+    pub fn load_scan_cache(&self) -> ScanCache { self.load(&cache_file("scan_cache")) }
+
+    pub fn save_usage(&self, usage: &UsageStats) { self.save("usage_stats_v2.bin", usage); }
+    pub fn load_usage(&self) -> UsageStats { self.load("usage_stats_v2.bin") }
+    // 旧版本的条目缓存 (含使用次数)，只在迁移时读取
+    pub fn load_legacy_apps(&self) -> Vec<LegacySearchResult> { self.load("apps_cache_v2.bin") }
 
-    pub fn save_habits(&self, habits: &UserHabits) { self.save("user_habits.bin", habits); }
-    pub fn load_habits(&self) -> UserHabits { self.load("user_habits.bin") }
+    pub fn save_habits(&self, habits: &UserHabits) { self.save("user_habits_v3.bin", habits); }
+    pub fn load_habits(&self) -> UserHabits { self.load("user_habits_v3.bin") }
+    // 旧版本的习惯记录 (搜索词 -> 条目)，只在迁移时读取
+    pub fn load_legacy_habits(&self) -> LegacyUserHabits { self.load("user_habits.bin") }
 
-    pub fn save_settings(&self, settings: &AppSettings) { self.save("settings.bin", settings); }
-    pub fn load_settings(&self) -> AppSettings { self.load("settings.bin") }
+    pub fn save_settings(&self, settings: &AppSettings) { self.save_json("settings.json", settings); }
+    pub fn load_settings(&self) -> Option<AppSettings> { self.load_json("settings.json") }
+    // 旧版本的设置 (bincode)，只在迁移时读取
+    pub fn load_legacy_settings(&self) -> LegacyAppSettings { self.load("settings.bin") }
 }
\ No newline at end of file
@@ -0,0 +1,473 @@
+use crate::models::PinyinReading;
+use crate::normalize;
+use pinyin::ToPinyinMulti;
+use std::borrow::Cow;
+use std::ops::Range;
+
+// ==========================================
+// 音译：名称的拉丁字母读法
+// ==========================================
+// 每种文字由一个 Transliterator 给出每个字符的读音 (全拼片段, 首字母片段)，逐字拼接起来就是一种读法 (PinyinReading)。
+// 设置中按语言启用 (AppSettings::transliteration)：
+// - "zh" 汉字 -> 拼音，多音字给出多个读音
+// - "ja" 假名 -> 平文式罗马字："ファイル" -> "fairu"、"きっぷ" -> "kippu"
+// - "ko" 韩文 -> 文化观光部 2000 年式罗马字："카카오톡" -> "kakaotok"，另外生成一种初声读法 "ㅋㅋㅇㅌ"
+// 没有启用或不认识的字符按归一化的结果保留 ("Café" -> "cafe"，见 normalize.rs)
+
+// 多音字最多取几个读音 (按常用程度排列)，以及每个名称最多生成几种读音组合，避免组合爆炸
+const MAX_CHAR_READINGS: usize = 3;
+const MAX_COMBINATIONS: usize = 16;
+
+pub type Reading = (Cow<'static, str>, Cow<'static, str>); // (全拼片段, 首字母片段)
+
+pub trait Transliterator: Sync {
+    // 第 i 个字符的读音，常用的在前；不属于这种文字时返回 None。
+    // 读音可能受前后字符影响 (假名的拗音、促音)，所以传入整个名称
+    fn readings(&self, chars: &[char], i: usize) -> Option<Vec<Reading>>;
+
+    // 第 i 个字符在另一种整体读法中的写法 (韩文的初声)，没有时返回 None
+    fn alternate(&self, _chars: &[char], _i: usize) -> Option<&'static str> {
+        None
+    }
+}
+
+// 设置中使用的语言名
+pub const LANGUAGES: &[(&str, &dyn Transliterator)] = &[("zh", &Pinyin), ("ja", &Romaji), ("ko", &Hangul)];
+
+pub struct Transliteration {
+    enabled: Vec<&'static dyn Transliterator>,
+}
+
+impl Transliteration {
+    pub fn new(languages: &[String]) -> Self {
+        Self {
+            enabled: LANGUAGES.iter()
+                .filter(|(name, _)| languages.iter().any(|l| l == name))
+                .map(|&(_, t)| t)
+                .collect(),
+        }
+    }
+
+    // 返回 (每个字都取最常用读音的结果, 其他读音组合)
+    pub fn readings(&self, name: &str) -> (PinyinReading, Vec<PinyinReading>) {
+        let folded = Folded::new(name);
+        let (primary, alternatives) = self.char_readings(name, &folded);
+
+        let mut readings: Vec<PinyinReading> = Vec::new();
+        let mut push = |segments: &[(&str, &str)]| {
+            let full: String = segments.iter().map(|s| s.0).collect();
+            if !readings.iter().any(|r| r.full == full) {
+                readings.push(PinyinReading {
+                    full,
+                    acronym: segments.iter().map(|s| s.1).collect(),
+                    syllables: segments.iter().map(|s| s.0.len().min(u8::MAX as usize) as u8).collect(),
+                });
+            }
+        };
+        combinations(&primary, &alternatives, &mut push);
+
+        // 有字符存在另一种写法时 (韩文初声)，再加一种这些字都换掉的读法
+        let mut replaced = false;
+        let segments: Vec<(&str, &str)> = primary.iter().enumerate()
+            .map(|(i, (full, abbr))| {
+                let alternate = folded.single(i)
+                    .and_then(|j| self.enabled.iter().find_map(|t| t.alternate(&folded.chars, j)));
+                match alternate {
+                    Some(alternate) => {
+                        replaced = true;
+                        (alternate, alternate)
+                    }
+                    None => (full.as_ref(), abbr.as_ref()),
+                }
+            })
+            .collect();
+        if replaced {
+            push(&segments);
+        }
+
+        let mut readings = readings.into_iter();
+        let primary = readings.next().unwrap_or_default();
+        (primary, readings.collect())
+    }
+
+    // 每个字符最常用的读音，以及有多个读音的字符的其他读音 (字符下标, 读音)
+    fn char_readings(&self, name: &str, folded: &Folded) -> (Vec<Reading>, Vec<(usize, Vec<Reading>)>) {
+        let mut primary = Vec::with_capacity(folded.spans.len());
+        let mut alternatives = Vec::new();
+
+        for (i, c) in name.chars().enumerate() {
+            // 优化：英文字符直接追加，不必询问各种文字
+            if c.is_ascii() {
+                let lower = Cow::Borrowed(ascii_lowercase(c));
+                primary.push((lower.clone(), lower));
+                continue;
+            }
+            let readings = folded.single(i)
+                .and_then(|j| self.enabled.iter().find_map(|t| t.readings(&folded.chars, j)))
+                .filter(|r| !r.is_empty());
+            match readings {
+                Some(readings) => {
+                    let mut readings = readings.into_iter();
+                    primary.extend(readings.next());
+                    let others: Vec<Reading> = readings.collect();
+                    if !others.is_empty() {
+                        alternatives.push((i, others));
+                    }
+                }
+                None => {
+                    // 处理其他语言或符号；并入前一个字符的浊点等为空片段
+                    let folded: Cow<str> = Cow::Owned(folded.chars[folded.spans[i].clone()].iter().collect());
+                    primary.push((folded.clone(), folded));
+                }
+            }
+        }
+        (primary, alternatives)
+    }
+}
+
+// 整个名称归一化后的字符 (见 normalize::fold_chars)，各种文字按它查读音：
+// 半角片假名与后面的浊点组合为一个全角假名 ("ｶﾞ" -> "ガ")，拗音、促音也能看到相邻的假名。
+// spans 为每个原文字符对应的归一化字符范围，读音仍按原文字符对齐 (高亮用)
+struct Folded {
+    chars: Vec<char>,
+    spans: Vec<Range<usize>>,
+}
+
+impl Folded {
+    fn new(name: &str) -> Self {
+        let folded = normalize::fold_chars(name);
+        let mut spans = Vec::new();
+        let mut start = 0;
+        for i in 0..name.chars().count() {
+            let end = start + folded[start..].iter().take_while(|&&(_, origin)| origin == i).count();
+            spans.push(start..end);
+            start = end;
+        }
+        Self { chars: folded.into_iter().map(|(c, _)| c).collect(), spans }
+    }
+
+    // 第 i 个原文字符归一化为单个字符时，该字符在 chars 中的下标
+    fn single(&self, i: usize) -> Option<usize> {
+        let span = &self.spans[i];
+        (span.len() == 1).then_some(span.start)
+    }
+}
+
+// 逐个读音组合给出每个字符的 (全拼片段, 首字母片段)。
+// 第一种组合全部取最常用读音，之后依次替换 1 个、2 个...多音字的读音，如 "重庆" -> "zhongqing" "chongqing" "tongqing"
+fn combinations(primary: &[Reading], alternatives: &[(usize, Vec<Reading>)], f: &mut impl FnMut(&[(&str, &str)])) {
+    let base: Vec<(&str, &str)> = primary.iter().map(|(a, b)| (a.as_ref(), b.as_ref())).collect();
+    f(&base);
+
+    // 每个组合为一组替换 (alternatives 下标, 读音下标)，下标递增，不会重复
+    let mut count = 1;
+    let mut level: Vec<Vec<(usize, usize)>> = vec![Vec::new()];
+    let mut segments = base.clone();
+    while !level.is_empty() {
+        let mut next = Vec::new();
+        for combo in &level {
+            let from = combo.last().map_or(0, |&(k, _)| k + 1);
+            for (k, (_, readings)) in alternatives.iter().enumerate().skip(from) {
+                for r in 0..readings.len() {
+                    if count >= MAX_COMBINATIONS {
+                        return;
+                    }
+                    let mut combo = combo.clone();
+                    combo.push((k, r));
+                    segments.copy_from_slice(&base);
+                    for &(k, r) in &combo {
+                        let (i, readings) = &alternatives[k];
+                        segments[*i] = (readings[r].0.as_ref(), readings[r].1.as_ref());
+                    }
+                    f(&segments);
+                    count += 1;
+                    next.push(combo);
+                }
+            }
+        }
+        level = next;
+    }
+}
+
+// ASCII 字符的小写形式，不必为每个字符分配字符串
+fn ascii_lowercase(c: char) -> &'static str {
+    const TABLE: [u8; 128] = {
+        let mut table = [0u8; 128];
+        let mut i = 0;
+        while i < 128 {
+            table[i] = (i as u8).to_ascii_lowercase();
+            i += 1;
+        }
+        table
+    };
+    let i = c as usize;
+    std::str::from_utf8(&TABLE[i..i + 1]).unwrap_or_default()
+}
+
+// 首字母片段：读音的第一个字母
+fn first_letter(s: &'static str) -> &'static str {
+    s.char_indices().nth(1).map_or(s, |(end, _)| &s[..end])
+}
+
+// ------------------------------------------
+// 汉字 -> 拼音
+// ------------------------------------------
+struct Pinyin;
+
+impl Transliterator for Pinyin {
+    fn readings(&self, chars: &[char], i: usize) -> Option<Vec<Reading>> {
+        let multi = chars[i].to_pinyin_multi()?;
+        // 不同声调的读音去掉声调后可能相同 (如 "行" 的 xíng / xìng)
+        let mut plains: Vec<&'static str> = Vec::new();
+        for p in multi {
+            if !plains.contains(&p.plain()) && plains.len() < MAX_CHAR_READINGS {
+                plains.push(p.plain());
+            }
+        }
+        Some(plains.into_iter().map(|plain| {
+            // ü 按输入法的习惯写作 v ("女" -> "nv")
+            let full: Cow<str> = if plain.contains('ü') { Cow::Owned(plain.replace('ü', "v")) } else { Cow::Borrowed(plain) };
+            (full, Cow::Borrowed(first_letter(plain)))
+        }).collect())
+    }
+}
+
+// ------------------------------------------
+// 假名 -> 平文式罗马字
+// ------------------------------------------
+struct Romaji;
+
+// 平假名 U+3041 (ぁ) ~ U+3096 (ゖ)，片假名先换成对应的平假名；っ 由后一个假名决定
+const KANA: [&str; 86] = [
+    "a", "a", "i", "i", "u", "u", "e", "e", "o", "o",
+    "ka", "ga", "ki", "gi", "ku", "gu", "ke", "ge", "ko", "go",
+    "sa", "za", "shi", "ji", "su", "zu", "se", "ze", "so", "zo",
+    "ta", "da", "chi", "ji", "", "tsu", "zu", "te", "de", "to", "do",
+    "na", "ni", "nu", "ne", "no",
+    "ha", "ba", "pa", "hi", "bi", "pi", "fu", "bu", "pu", "he", "be", "pe", "ho", "bo", "po",
+    "ma", "mi", "mu", "me", "mo",
+    "ya", "ya", "yu", "yu", "yo", "yo",
+    "ra", "ri", "ru", "re", "ro",
+    "wa", "wa", "i", "e", "o", "n", "vu", "ka", "ke",
+];
+
+fn kana(c: char) -> Option<&'static str> {
+    let c = match c {
+        'ァ'..='ヶ' => char::from_u32(c as u32 - 0x60)?,
+        'ヷ' => return Some("va"),
+        'ヸ' => return Some("vi"),
+        'ヹ' => return Some("ve"),
+        'ヺ' => return Some("vo"),
+        _ => c,
+    };
+    match c {
+        'ぁ'..='ゖ' => Some(KANA[c as usize - 'ぁ' as usize]),
+        _ => None,
+    }
+}
+
+// 拗音的小 ゃ ゅ ょ
+fn is_small_y(c: char) -> bool {
+    matches!(c, 'ゃ' | 'ゅ' | 'ょ' | 'ャ' | 'ュ' | 'ョ')
+}
+
+// 外来语的小 ぁ ぃ ぅ ぇ ぉ ("ファ" -> "fa"、"ティ" -> "ti")
+fn is_small_vowel(c: char) -> bool {
+    matches!(c, 'ぁ' | 'ぃ' | 'ぅ' | 'ぇ' | 'ぉ' | 'ァ' | 'ィ' | 'ゥ' | 'ェ' | 'ォ')
+}
+
+fn is_small(c: char) -> bool {
+    is_small_y(c) || is_small_vowel(c) || matches!(c, 'っ' | 'ッ')
+}
+
+impl Romaji {
+    // 不考虑长音符的读音 (全拼片段)
+    fn segment(&self, chars: &[char], i: usize) -> Option<&'static str> {
+        let c = chars[i];
+        let romaji = kana(c)?;
+        let next = chars.get(i + 1).copied();
+        let prev = i.checked_sub(1).map(|j| chars[j]).filter(|&p| !is_small(p)).and_then(kana);
+
+        // 促音：重复后一个音节的辅音 ("きって" -> "kitte"、"マッチ" -> "matchi")
+        if matches!(c, 'っ' | 'ッ') {
+            let following = next.and_then(kana).unwrap_or("");
+            return Some(if following.starts_with("ch") { "t" } else { first_letter(following) });
+        }
+        // 拗音：前一个音节去掉 i，"しゃ" -> "sh" + "a"、"きゃ" -> "k" + "ya"
+        if is_small_y(c) {
+            return Some(match prev {
+                Some("shi" | "chi" | "ji") => &romaji[1..],
+                _ => romaji,
+            });
+        }
+        if next.is_some_and(is_small_y) && romaji.len() > 1 && romaji.ends_with('i') {
+            return Some(&romaji[..romaji.len() - 1]);
+        }
+        // 小元音：前一个音节去掉元音，单独的元音变为半元音 ("ウィ" -> "wi"、"イェ" -> "ye")
+        if next.is_some_and(is_small_vowel) && !is_small(c) {
+            return Some(match romaji {
+                "u" => "w",
+                "i" => "y",
+                "a" | "e" | "o" => romaji,
+                _ => &romaji[..romaji.len() - 1],
+            });
+        }
+        Some(romaji)
+    }
+}
+
+impl Transliterator for Romaji {
+    fn readings(&self, chars: &[char], i: usize) -> Option<Vec<Reading>> {
+        // 长音符 "ー" 通常不输入 ("コーヒー" -> "kohi")，作为分隔符跳过；也可以重复前一个元音 ("koohii")
+        if chars[i] == 'ー' {
+            let mut readings: Vec<Reading> = vec![(Cow::Borrowed("-"), Cow::Borrowed("-"))];
+            let vowel = (0..i).rev()
+                .find_map(|j| self.segment(chars, j).filter(|s| !s.is_empty()))
+                .and_then(|s| s.char_indices().last().map(|(k, _)| &s[k..]))
+                .filter(|v| matches!(*v, "a" | "i" | "u" | "e" | "o"));
+            readings.extend(vowel.map(|v| (Cow::Borrowed(v), Cow::Borrowed(v))));
+            return Some(readings);
+        }
+        let segment = self.segment(chars, i)?;
+        Some(vec![(Cow::Borrowed(segment), Cow::Borrowed(first_letter(segment)))])
+    }
+}
+
+// ------------------------------------------
+// 韩文 -> 罗马字 / 初声
+// ------------------------------------------
+struct Hangul;
+
+// 音节 = 0xAC00 + (初声 * 21 + 中声) * 28 + 终声
+const CHOSEONG: [&str; 19] = ["g", "kk", "n", "d", "tt", "r", "m", "b", "pp", "s", "ss", "", "j", "jj", "ch", "k", "t", "p", "h"];
+const JUNGSEONG: [&str; 21] = [
+    "a", "ae", "ya", "yae", "eo", "e", "yeo", "ye", "o", "wa", "wae", "oe", "yo", "u", "wo", "we", "wi", "yu", "eu", "ui", "i",
+];
+// 终声按代表音
+const JONGSEONG: [&str; 28] = [
+    "", "k", "k", "k", "n", "n", "n", "t", "l", "k", "m", "l", "l", "l", "p", "l", "m", "p", "p", "t", "t", "ng", "t", "t", "k", "t", "p", "t",
+];
+// 初声的兼容字母，用于初声搜索 ("ㅋㅋㅇㅌ" -> "카카오톡")
+const CHOSEONG_JAMO: [&str; 19] = ["ㄱ", "ㄲ", "ㄴ", "ㄷ", "ㄸ", "ㄹ", "ㅁ", "ㅂ", "ㅃ", "ㅅ", "ㅆ", "ㅇ", "ㅈ", "ㅉ", "ㅊ", "ㅋ", "ㅌ", "ㅍ", "ㅎ"];
+
+// (初声, 中声, 终声) 下标
+fn hangul_syllable(c: char) -> Option<(usize, usize, usize)> {
+    let s = (c as u32).checked_sub(0xAC00).filter(|&s| s < 19 * 21 * 28)? as usize;
+    Some((s / (21 * 28), s % (21 * 28) / 28, s % 28))
+}
+
+impl Transliterator for Hangul {
+    fn readings(&self, chars: &[char], i: usize) -> Option<Vec<Reading>> {
+        let (l, v, t) = hangul_syllable(chars[i])?;
+        let full = format!("{}{}{}", CHOSEONG[l], JUNGSEONG[v], JONGSEONG[t]);
+        // 首字母：初声的第一个字母，没有初声 (ㅇ) 时为元音的第一个字母
+        let first = first_letter(if CHOSEONG[l].is_empty() { JUNGSEONG[v] } else { CHOSEONG[l] });
+        Some(vec![(Cow::Owned(full), Cow::Borrowed(first))])
+    }
+
+    fn alternate(&self, chars: &[char], i: usize) -> Option<&'static str> {
+        hangul_syllable(chars[i]).map(|(l, _, _)| CHOSEONG_JAMO[l])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pinyin_match::match_syllables;
+
+    fn translit(languages: &[&str]) -> Transliteration {
+        Transliteration::new(&languages.iter().map(|l| l.to_string()).collect::<Vec<_>>())
+    }
+
+    // 查询词与名称经过同样的归一化后，能在某种读法中匹配
+    fn matches(t: &Transliteration, name: &str, query: &str) -> bool {
+        let query = normalize::fold(query);
+        let (primary, others) = t.readings(name);
+        std::iter::once(&primary).chain(&others).any(|r| match_syllables(r, &query).is_some())
+    }
+
+    #[test]
+    fn half_width_voiced_kana_composes_before_transliterating() {
+        let t = translit(&["ja"]);
+        let (reading, _) = t.readings("ｶﾞｲﾄﾞ");
+        assert_eq!(reading.full, "gaido");
+        // 浊点并入前一个字符，读音仍与原文的 5 个字符对齐
+        assert_eq!(reading.syllables, vec![2, 0, 1, 2, 0]);
+        assert!(matches(&t, "ｶﾞｲﾄﾞ", "gaido"));
+        assert!(matches(&t, "ｷﾞｬﾗﾘｰ", "gyarari"));
+        assert_eq!(t.readings("ガイド").0.full, "gaido");
+    }
+
+    #[test]
+    fn chosung_query_matches_after_fold() {
+        let t = translit(&["ko"]);
+        assert_eq!(normalize::fold("ㅋㅋㅇㅌ"), "ㅋㅋㅇㅌ");
+        assert!(matches(&t, "카카오톡", "ㅋㅋㅇㅌ"));
+        assert!(matches(&t, "카카오톡", "kakaotok"));
+    }
+
+    fn fulls(t: &Transliteration, name: &str) -> Vec<String> {
+        let (primary, others) = t.readings(name);
+        std::iter::once(primary).chain(others).map(|r| r.full).collect()
+    }
+
+    #[test]
+    fn polyphonic_characters_produce_combinations() {
+        let t = translit(&["zh"]);
+        let (primary, others) = t.readings("重庆地图");
+        assert_eq!(primary.full, "zhongqingditu");
+        assert_eq!(primary.acronym, "zqdt");
+        assert_eq!(primary.syllables, vec![5, 4, 2, 2]);
+        assert!(others.iter().any(|r| r.full == "chongqingditu" && r.acronym == "cqdt"));
+        // ü 写作 v
+        assert_eq!(t.readings("女").0.full, "nv");
+        // 组合数有上限
+        assert!(fulls(&t, "行长重还长行").len() <= MAX_COMBINATIONS);
+    }
+
+    #[test]
+    fn kana_romaji() {
+        let t = translit(&["ja"]);
+        let full = |name: &str| t.readings(name).0.full;
+        assert_eq!(full("ファイル"), "fairu");
+        assert_eq!(full("きっぷ"), "kippu");
+        assert_eq!(full("マッチ"), "matchi");
+        assert_eq!(full("しゃしん"), "shashin");
+        assert_eq!(full("きょうと"), "kyouto");
+        assert_eq!(full("ティッシュ"), "tisshu");
+        assert_eq!(full("ウィンドウ"), "windou");
+        // 长音符可以不输入，也可以重复前一个元音
+        let (primary, others) = t.readings("コーヒー");
+        assert_eq!(primary.full, "ko-hi-");
+        assert_eq!(primary.acronym, "k-h-");
+        assert!(others.iter().any(|r| r.full == "koohii"));
+        assert!(matches(&t, "コーヒー", "kohi"));
+    }
+
+    #[test]
+    fn hangul_romanization_and_chosung() {
+        let t = translit(&["ko"]);
+        let (primary, others) = t.readings("카카오톡");
+        assert_eq!(primary.full, "kakaotok");
+        assert_eq!(primary.acronym, "kkot");
+        assert_eq!(others.last().unwrap().full, "ㅋㅋㅇㅌ");
+        assert_eq!(t.readings("한글").0.full, "hangeul");
+        // 其他字符在初声读法中保持不变
+        assert_eq!(t.readings("네이버 지도 PC").1.last().unwrap().full, "ㄴㅇㅂ ㅈㄷ pc");
+        assert!(matches(&t, "네이버", "neibeo"));
+        assert!(!matches(&t, "네이버", "naver"));
+    }
+
+    #[test]
+    fn disabled_languages_keep_folded_text() {
+        let zh = translit(&["zh"]);
+        assert_eq!(fulls(&zh, "ファイル"), vec!["ファイル"]);
+        let none = translit(&[]);
+        assert_eq!(fulls(&none, "微信"), vec!["微信"]);
+        assert!(none.readings("카카오").1.is_empty());
+        // 读音片段与原文字符一一对应，"ß" 展开为两个字母
+        let (reading, _) = translit(&["zh", "ja", "ko"]).readings("Straße 地图");
+        assert_eq!(reading.full, "strasse ditu");
+        assert_eq!(reading.syllables, vec![1, 1, 1, 1, 2, 1, 1, 2, 2]);
+    }
+}
@@ -0,0 +1,124 @@
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::time::{Duration, Instant};
+
+// ==========================================
+// 文件系统监听 (Linux: inotify, macOS: FSEvents, Windows: ReadDirectoryChangesW)
+// ==========================================
+// 安装软件时往往在短时间内产生成百上千个事件，这里做防抖：
+// 安静 DEBOUNCE 之后再把这一批变化的路径交给回调，持续有事件时最多等待 MAX_BATCH_DELAY
+
+const DEBOUNCE: Duration = Duration::from_millis(500);
+const MAX_BATCH_DELAY: Duration = Duration::from_secs(5);
+
+// 监听线程的句柄：扫描配置变化时替换监听的目录
+#[derive(Clone)]
+pub struct WatchHandle {
+    tx: Sender<Message>,
+}
+
+impl WatchHandle {
+    pub fn set_roots(&self, roots: Vec<(PathBuf, bool)>) {
+        let _ = self.tx.send(Message::Roots(roots));
+    }
+}
+
+enum Message {
+    Event(notify::Result<Event>),
+    Roots(Vec<(PathBuf, bool)>),
+}
+
+// 在后台线程监听 roots (路径, 是否递归)，每批变化调用一次 on_change
+pub fn spawn<F>(roots: Vec<(PathBuf, bool)>, on_change: F) -> WatchHandle
+where
+    F: Fn(Vec<PathBuf>) + Send + 'static,
+{
+    let (tx, rx) = mpsc::channel();
+    let handle = WatchHandle { tx: tx.clone() };
+    std::thread::spawn(move || {
+        // watcher 必须在整个线程生命周期内存活，drop 之后监听就停止了
+        let mut watcher = match notify::recommended_watcher(move |event| {
+            let _ = tx.send(Message::Event(event));
+        }) {
+            Ok(w) => w,
+            Err(e) => {
+                eprintln!("Failed to create file watcher: {}", e);
+                return;
+            }
+        };
+
+        let mut watched = Vec::new();
+        watch_roots(&mut watcher, &mut watched, roots);
+
+        while let Some(batch) = next_batch(&rx, &mut watcher, &mut watched) {
+            if !batch.is_empty() {
+                on_change(batch);
+            }
+        }
+    });
+    handle
+}
+
+// 替换监听的目录：取消之前的，再监听新的
+fn watch_roots(watcher: &mut impl Watcher, watched: &mut Vec<PathBuf>, roots: Vec<(PathBuf, bool)>) {
+    for root in watched.drain(..) {
+        let _ = watcher.unwatch(&root);
+    }
+    for (root, recursive) in roots {
+        let mode = if recursive { RecursiveMode::Recursive } else { RecursiveMode::NonRecursive };
+        match watcher.watch(&root, mode) {
+            Ok(()) => watched.push(root),
+            Err(e) => eprintln!("Failed to watch {}: {}", root.display(), e),
+        }
+    }
+    println!("Watching {} directories for changes.", watched.len());
+}
+
+// 阻塞直到收到第一个事件，然后收集到安静为止；通道关闭时返回 None。
+// 期间收到的新目录立即生效，已收集的变化仍在这一批中交给回调
+fn next_batch(rx: &Receiver<Message>, watcher: &mut impl Watcher, watched: &mut Vec<PathBuf>) -> Option<Vec<PathBuf>> {
+    let mut paths = BTreeSet::new();
+    loop {
+        match rx.recv().ok()? {
+            Message::Event(event) => {
+                collect_event(event, watched, &mut paths);
+                break;
+            }
+            Message::Roots(roots) => watch_roots(watcher, watched, roots),
+        }
+    }
+
+    let deadline = Instant::now() + MAX_BATCH_DELAY;
+    loop {
+        let timeout = DEBOUNCE.min(deadline.saturating_duration_since(Instant::now()));
+        match rx.recv_timeout(timeout) {
+            Ok(Message::Event(event)) => collect_event(event, watched, &mut paths),
+            Ok(Message::Roots(roots)) => watch_roots(watcher, watched, roots),
+            Err(RecvTimeoutError::Timeout) => break,
+            Err(RecvTimeoutError::Disconnected) => return None,
+        }
+    }
+    Some(paths.into_iter().collect())
+}
+
+fn collect_event(event: notify::Result<Event>, roots: &[PathBuf], paths: &mut BTreeSet<PathBuf>) {
+    let event = match event {
+        Ok(e) => e,
+        Err(e) => {
+            eprintln!("File watcher error: {}", e);
+            return;
+        }
+    };
+    // 只读访问不会改变索引
+    if matches!(event.kind, EventKind::Access(_)) {
+        return;
+    }
+    // 是否与索引相关 (隐藏文件、被规则排除的路径等) 交给 scanner::apply_changes 判断
+    for path in event.paths {
+        if roots.iter().any(|r| path.starts_with(r)) {
+            paths.insert(path);
+        }
+    }
+}